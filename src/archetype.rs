@@ -0,0 +1,180 @@
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::post::file::{File as PostFile, FileOpts};
+use crate::post::value::Value;
+use crate::post::PostParseError;
+
+/// Used when there is no `default.reb` in the archetype directory
+pub const DEFAULT_ARCHETYPE: &str = "Title: {{title}}
+Author: {{author}}
+Date: {{date}}
+ID: {{id}}
+
+Post body starts here
+";
+
+/// Values for the `{{title}}`, `{{author}}`, `{{date}}`, `{{id}}` and `{{tags}}` placeholders
+pub struct Placeholders {
+    pub title: String,
+    pub author: String,
+    pub date: DateTime<FixedOffset>,
+    pub id: String,
+    pub tags: Vec<String>,
+}
+impl Placeholders {
+    fn replace(&self, s: &str) -> String {
+        s.replace("{{title}}", &self.title)
+            .replace("{{author}}", &self.author)
+            .replace("{{date}}", &self.date.to_rfc2822())
+            .replace("{{id}}", &self.id)
+            .replace("{{tags}}", &self.tags.join(", "))
+    }
+
+    /// The new value for a header, or None if the header should be removed. A value that is
+    /// exactly one placeholder gets that placeholder's type, so `{{date}}` becomes a date in TOML
+    /// front matter and `{{tags}}` becomes a list.
+    fn replace_value(&self, v: &Value) -> Option<Value> {
+        match v {
+            Value::String(s) if s == "{{date}}" => Some(Value::Date(self.date)),
+            Value::String(s) if s == "{{tags}}" => {
+                if self.tags.is_empty() {
+                    None
+                } else {
+                    Some(self.tags.clone().into())
+                }
+            }
+            Value::String(s) => {
+                let s = self.replace(s);
+                if s.trim().is_empty() {
+                    None
+                } else {
+                    Some(Value::String(s))
+                }
+            }
+            Value::List(items) => {
+                let mut out = vec![];
+                for item in items {
+                    match item {
+                        Value::String(s) if s == "{{tags}}" => {
+                            out.extend(self.tags.iter().cloned().map(Value::String))
+                        }
+                        item => out.extend(self.replace_value(item)),
+                    }
+                }
+                Some(Value::List(out))
+            }
+            v => Some(v.clone()),
+        }
+    }
+}
+
+/// Read the archetype called *kind* from *dname*. Without a *kind*, `default.reb` is used if it
+/// exists and the built-in archetype otherwise.
+pub fn load_archetype(dname: &str, kind: Option<&str>) -> Result<String, String> {
+    let fname = Path::new(dname).join(format!("{}.reb", kind.unwrap_or("default")));
+    match fs::read_to_string(&fname) {
+        Ok(s) => Ok(s),
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound && kind.is_none() => {
+            Ok(DEFAULT_ARCHETYPE.to_string())
+        }
+        Err(e) => Err(format!("{}: {}", fname.display(), e)),
+    }
+}
+
+/// Make a new post from the archetype *text*, filling in placeholders in its headers and body.
+/// Headers whose value ends up empty are removed, and any required headers the archetype left
+/// out are added.
+pub fn fill(text: &str, p: &Placeholders) -> Result<PostFile, PostParseError> {
+    let opts = FileOpts {
+        strict_headers: false,
+        page: false,
+    };
+    let buf = Box::new(Cursor::new(text.as_bytes().to_vec()));
+    let mut pf = PostFile::new_from_buf(buf, None, Some(opts))?;
+    let headers: Vec<(String, Value)> = pf
+        .headers()
+        .iter()
+        .map(|h| (h.key.clone(), h.value.clone()))
+        .collect();
+    for (key, value) in headers {
+        match p.replace_value(&value) {
+            Some(ref new) if *new == value => {}
+            Some(new) => pf.set_header(&key, new)?,
+            None => pf.remove_header(&key)?,
+        }
+    }
+    let body = p.replace(&pf.get_body());
+    pf.set_body(&body)?;
+    for (key, value) in &[
+        ("Title", Value::String(p.title.clone())),
+        ("Author", Value::String(p.author.clone())),
+        ("Date", Value::Date(p.date)),
+        ("ID", Value::String(p.id.clone())),
+    ] {
+        if !pf.has_header(key) {
+            pf.set_header(key, value.clone())?;
+        }
+    }
+    Ok(pf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fill, Placeholders, DEFAULT_ARCHETYPE};
+    use crate::post::value::parse_date;
+
+    fn placeholders(tags: &[&str]) -> Placeholders {
+        Placeholders {
+            title: "My Post".to_string(),
+            author: "Me".to_string(),
+            date: parse_date("Thu, 18 Oct 2018 10:00:00 +0000").unwrap(),
+            id: "abc".to_string(),
+            tags: tags.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn default_archetype() {
+        let pf = fill(DEFAULT_ARCHETYPE, &placeholders(&[])).unwrap();
+        assert_eq!(
+            pf.to_string(),
+            "Title: My Post
+Author: Me
+Date: Thu, 18 Oct 2018 10:00:00 +0000
+ID: abc
+
+Post body starts here
+"
+        );
+    }
+
+    #[test]
+    fn tags_and_body() {
+        let text = "# A review\nTitle: Review: {{title}}\nTags: {{tags}}\n\n# {{title}}\n";
+        let pf = fill(text, &placeholders(&[])).unwrap();
+        assert!(!pf.has_header("tags"));
+        assert_eq!(pf.title(), "Review: My Post");
+        assert_eq!(pf.get_body(), "# My Post\n");
+        assert!(pf
+            .to_string()
+            .starts_with("# A review\nTitle: Review: My Post\n"));
+        assert!(pf.has_header("id"));
+        let pf = fill(text, &placeholders(&["a", "b"])).unwrap();
+        assert_eq!(pf.tags(), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn toml_archetype() {
+        let text = "+++\ntitle = \"{{title}}\"\ndate = \"{{date}}\"\ntags = [\"review\", \"{{tags}}\"]\n+++\n\nBody";
+        let pf = fill(text, &placeholders(&["a"])).unwrap();
+        assert_eq!(pf.tags(), vec!["review", "a"]);
+        assert!(pf
+            .to_string()
+            .contains("date = 2018-10-18T10:00:00+00:00\n"));
+        assert_eq!(pf.authors(), vec!["Me"]);
+    }
+}
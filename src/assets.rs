@@ -0,0 +1,453 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, create_dir_all, metadata};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::util::fs::recursive_find_files;
+use crate::util::hash::fnv1a;
+use crate::util::html::rewrite_attrs;
+use crate::util::json;
+
+/// Files with these extensions get a content hash in their name when fingerprinting is on
+const FINGERPRINT_EXTS: &[&str] = &[
+    "css", "js", "png", "jpg", "jpeg", "gif", "svg", "webp", "ico",
+];
+
+/// Written to the root of the static output directory, mapping each file's original path to the
+/// path it was written to
+pub const MANIFEST_FNAME: &str = "manifest.json";
+
+/// Number of hex digits of the content hash put in fingerprinted names
+const HASH_LEN: usize = 6;
+
+/// Where the static output directory is served from
+const STATIC_URL: &str = "/static/";
+
+/// Attributes whose value is a URL that can point at a static file
+const URL_ATTRS: &[&str] = &["href", "src", "poster"];
+
+/// Split *rel* into everything before the extension and the extension, if it has one
+pub fn split_ext(rel: &str) -> (&str, Option<&str>) {
+    let name_start = rel.rfind('/').map(|i| i + 1).unwrap_or(0);
+    match rel[name_start..].rfind('.') {
+        // A leading dot is a hidden file, not an extension
+        Some(0) | None => (rel, None),
+        Some(i) => (&rel[..name_start + i], Some(&rel[name_start + i + 1..])),
+    }
+}
+
+/// Whether *rel* is the kind of file that gets fingerprinted
+fn should_fingerprint(rel: &str) -> bool {
+    match split_ext(rel).1 {
+        Some(ext) => FINGERPRINT_EXTS.contains(&ext.to_lowercase().as_str()),
+        None => false,
+    }
+}
+
+fn is_css(rel: &str) -> bool {
+    split_ext(rel).1.map(|ext| ext.eq_ignore_ascii_case("css")) == Some(true)
+}
+
+/// Split *url* into its path and the query or fragment after it
+fn split_suffix(url: &str) -> (&str, &str) {
+    url.split_at(url.find(['?', '#']).unwrap_or(url.len()))
+}
+
+/// The name *rel* gets when its content is *bytes*, like `css/style.3f9a1c.css` for
+/// `css/style.css`
+pub fn fingerprinted_name(rel: &str, bytes: &[u8]) -> String {
+    let hash = format!("{:016x}", fnv1a(bytes));
+    let hash = &hash[..HASH_LEN];
+    match split_ext(rel) {
+        (stem, Some(ext)) => format!("{}.{}.{}", stem, hash, ext),
+        (stem, None) => format!("{}.{}", stem, hash),
+    }
+}
+
+/// Whether the file name *fname* looks like something `fingerprinted_name` made
+fn looks_fingerprinted(fname: &str) -> bool {
+    let (stem, ext) = split_ext(fname);
+    if ext.is_none() {
+        return false;
+    }
+    match split_ext(stem) {
+        (_, Some(hash)) => hash.len() == HASH_LEN && hash.chars().all(|c| c.is_ascii_hexdigit()),
+        _ => false,
+    }
+}
+
+/// The static files of a build. Files are added by their path relative to the output directory
+/// and are only written when they changed. Once everything is added, `finish` writes the manifest
+/// and removes fingerprinted files left over from earlier builds.
+pub struct StaticDir {
+    out_dname: PathBuf,
+    fingerprint: bool,
+    rebuild: bool,
+    /// Original relative path to the relative path it was written to
+    manifest: BTreeMap<String, String>,
+    written: HashSet<PathBuf>,
+    /// Files added with `add_file`, by relative path
+    files: BTreeMap<String, PathBuf>,
+    num_copied: usize,
+    num_skipped: usize,
+}
+
+impl StaticDir {
+    /// Static files go in *out_dname*. With *fingerprint*, CSS, JS and images get a content hash
+    /// in their name. With *rebuild*, every file is written even if it didn't change.
+    pub fn new(out_dname: &str, fingerprint: bool, rebuild: bool) -> Self {
+        StaticDir {
+            out_dname: PathBuf::from(out_dname),
+            fingerprint,
+            rebuild,
+            manifest: BTreeMap::new(),
+            written: HashSet::new(),
+            files: BTreeMap::new(),
+            num_copied: 0,
+            num_skipped: 0,
+        }
+    }
+
+    /// Whether something was already added at *rel*
+    pub fn contains(&self, rel: &str) -> bool {
+        self.manifest.contains_key(rel)
+    }
+
+    /// The absolute URL that *rel* ended up at
+    pub fn url(&self, rel: &str) -> String {
+        match self.manifest.get(rel) {
+            Some(out) => format!("/static/{}", out),
+            None => format!("/static/{}", rel),
+        }
+    }
+
+    /// Where *url* goes if it's a site path like `/static/img/a.png` and the file there was
+    /// written under another name
+    fn fingerprinted_url(&self, url: &str) -> Option<String> {
+        let (path, suffix) = split_suffix(url);
+        let rel = path.strip_prefix(STATIC_URL)?;
+        match self.manifest.get(rel) {
+            Some(out) if out != rel => Some(format!("{}{}{}", STATIC_URL, out, suffix)),
+            _ => None,
+        }
+    }
+
+    /// Point the links to static files in *html* at the names the files were written under, so
+    /// posts can keep linking to `/static/img/a.png` when fingerprinting is on
+    pub fn rewrite(&self, html: &str) -> String {
+        if !self.fingerprint {
+            return html.to_string();
+        }
+        rewrite_attrs(html, |_, attr, value| {
+            if URL_ATTRS.contains(&attr) {
+                self.fingerprinted_url(value)
+            } else if attr == "srcset" {
+                let mut changed = false;
+                let candidates: Vec<String> = value
+                    .split(',')
+                    .map(|c| {
+                        // Each candidate is a URL and then maybe a width or density
+                        let c = c.trim();
+                        let (url, descriptor) = c.split_at(c.find(' ').unwrap_or(c.len()));
+                        match self.fingerprinted_url(url) {
+                            Some(new) => {
+                                changed = true;
+                                new + descriptor
+                            }
+                            None => c.to_string(),
+                        }
+                    })
+                    .collect();
+                if changed {
+                    Some(candidates.join(", "))
+                } else {
+                    None
+                }
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Where the link *url* in a stylesheet in the directory *dir* goes, if it's a static file
+    /// that was written under another name. Links can be site paths or relative ones.
+    fn css_url(&self, dir: &str, url: &str) -> Option<String> {
+        if url.starts_with(STATIC_URL) {
+            return self.fingerprinted_url(url);
+        }
+        if url.is_empty() || url.starts_with(['/', '#']) || url.contains(':') {
+            return None;
+        }
+        let (path, suffix) = split_suffix(url);
+        let mut parts: Vec<&str> = dir.split('/').filter(|p| !p.is_empty()).collect();
+        for part in path.split('/') {
+            match part {
+                "" | "." => {}
+                ".." => {
+                    parts.pop()?;
+                }
+                part => parts.push(part),
+            }
+        }
+        let rel = parts.join("/");
+        let out = self.manifest.get(&rel)?;
+        if *out == rel {
+            return None;
+        }
+        // Fingerprinting only changes the file name, so the link keeps its directories
+        let name_start = |p: &str| p.rfind('/').map(|i| i + 1).unwrap_or(0);
+        Some(format!(
+            "{}{}{}",
+            &path[..name_start(path)],
+            &out[name_start(out)..],
+            suffix
+        ))
+    }
+
+    /// Point the `url()`s in the stylesheet *css* at *rel* at the names the files were written
+    /// under
+    fn rewrite_css(&self, rel: &str, css: &str) -> String {
+        let dir = &rel[..rel.rfind('/').map(|i| i + 1).unwrap_or(0)];
+        let mut out = String::with_capacity(css.len());
+        let mut rest = css;
+        while let Some(start) = rest.find("url(") {
+            let start = start + "url(".len();
+            let end = match rest[start..].find(')') {
+                Some(e) => start + e,
+                None => break,
+            };
+            let arg = &rest[start..end];
+            let url = arg.trim().trim_matches(|c| c == '"' || c == '\'');
+            out += &rest[..start];
+            match self.css_url(dir, url) {
+                Some(new) => out += &arg.replace(url, &new),
+                None => out += arg,
+            }
+            rest = &rest[end..];
+        }
+        out + rest
+    }
+
+    /// The URL of the first file at *stem* with any extension, for files whose extension depends
+    /// on what's in them
+    pub fn url_for_stem(&self, stem: &str) -> String {
+        match self.manifest.keys().find(|rel| split_ext(rel).0 == stem) {
+            Some(rel) => self.url(rel),
+            None => self.url(stem),
+        }
+    }
+
+    /// Add *bytes* as *rel*. *src_mtime* is when the source of the bytes last changed, if they
+    /// came from a file, and is used to tell if the output is up to date without reading it.
+    pub fn add_bytes(
+        &mut self,
+        rel: &str,
+        bytes: &[u8],
+        src_mtime: Option<SystemTime>,
+    ) -> Result<(), String> {
+        // Stylesheets link to other static files, which may have been renamed
+        let css;
+        let bytes = if self.fingerprint && is_css(rel) {
+            css = self.rewrite_css(rel, &String::from_utf8_lossy(bytes));
+            css.as_bytes()
+        } else {
+            bytes
+        };
+        let out_rel = if self.fingerprint && should_fingerprint(rel) {
+            fingerprinted_name(rel, bytes)
+        } else {
+            rel.to_string()
+        };
+        let fname = self.out_dname.join(&out_rel);
+        if self.rebuild || !Self::up_to_date(&fname, rel, &out_rel, bytes, src_mtime) {
+            debug!("Writing {} ...", fname.display());
+            if let Some(parent) = fname.parent() {
+                create_dir_all(parent).map_err(|e| format!("{}: {}", parent.display(), e))?;
+            }
+            fs::write(&fname, bytes).map_err(|e| format!("{}: {}", fname.display(), e))?;
+            self.num_copied += 1;
+        } else {
+            trace!("{} is up to date", fname.display());
+            self.num_skipped += 1;
+        }
+        self.written.insert(fname);
+        self.manifest.insert(rel.to_string(), out_rel);
+        Ok(())
+    }
+
+    /// Copy the file *src* to *rel*
+    pub fn add_file(&mut self, rel: &str, src: &Path) -> Result<(), String> {
+        let bytes = fs::read(src).map_err(|e| format!("{}: {}", src.display(), e))?;
+        let mtime = metadata(src).and_then(|m| m.modified()).ok();
+        self.add_bytes(rel, &bytes, mtime)?;
+        self.files.insert(rel.to_string(), src.to_path_buf());
+        Ok(())
+    }
+
+    /// The files added with `add_file` and where they came from
+    pub fn files(&self) -> Vec<(String, PathBuf)> {
+        self.files
+            .iter()
+            .map(|(rel, src)| (rel.clone(), src.clone()))
+            .collect()
+    }
+
+    /// Copy everything in the directory *src_dname*, keeping its structure. A missing directory
+    /// is not an error, since having static files is optional.
+    pub fn add_dir(&mut self, src_dname: &str) -> Result<(), String> {
+        let mut fnames = recursive_find_files(src_dname);
+        // Stylesheets come last, so the files they link to already have their names
+        fnames.sort_by_key(|f| (is_css(&f.to_string_lossy()), f.clone()));
+        for fname in fnames {
+            let rel = fname
+                .strip_prefix(src_dname)
+                .unwrap()
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .collect::<Vec<String>>()
+                .join("/");
+            self.add_file(&rel, &fname)?;
+        }
+        Ok(())
+    }
+
+    fn up_to_date(
+        fname: &Path,
+        rel: &str,
+        out_rel: &str,
+        bytes: &[u8],
+        src_mtime: Option<SystemTime>,
+    ) -> bool {
+        let meta = match metadata(fname) {
+            Ok(meta) => meta,
+            Err(_) => return false,
+        };
+        if meta.len() != bytes.len() as u64 {
+            return false;
+        }
+        // The name of a fingerprinted file already says what's in it
+        if out_rel != rel {
+            return true;
+        }
+        match (src_mtime, meta.modified()) {
+            (Some(src), Ok(out)) => out >= src,
+            _ => fs::read(fname).map(|b| b == bytes).unwrap_or(false),
+        }
+    }
+
+    /// Write the manifest and remove fingerprinted files that this build didn't write, which should
+    /// be done once after adding everything. Returns the number of files written, skipped because
+    /// they were unchanged, and removed.
+    pub fn finish(&mut self) -> Result<(usize, usize, usize), String> {
+        let manifest = json::object(self.manifest.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+        self.add_bytes(MANIFEST_FNAME, manifest.as_bytes(), None)?;
+        let mut num_removed = 0;
+        for fname in recursive_find_files(self.out_dname.to_str().unwrap()) {
+            if self.written.contains(&fname) {
+                continue;
+            }
+            let is_stale = match fname.file_name().and_then(|n| n.to_str()) {
+                Some(n) => looks_fingerprinted(n),
+                None => false,
+            };
+            if is_stale {
+                debug!("Removing stale {}", fname.display());
+                fs::remove_file(&fname).map_err(|e| format!("{}: {}", fname.display(), e))?;
+                num_removed += 1;
+            }
+        }
+        Ok((self.num_copied, self.num_skipped, num_removed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fingerprinted_name, looks_fingerprinted, StaticDir};
+    use crate::links::check_links;
+    use crate::site::SiteUrl;
+    use std::collections::HashMap;
+    use std::fs;
+
+    #[test]
+    fn names() {
+        let name = fingerprinted_name("css/style.css", b"body {}");
+        assert!(name.starts_with("css/style."));
+        assert!(name.ends_with(".css"));
+        assert_eq!(name.len(), "css/style.123456.css".len());
+        assert_ne!(name, fingerprinted_name("css/style.css", b"body { }"));
+        assert!(fingerprinted_name("a.b/LICENSE", b"").starts_with("a.b/LICENSE."));
+        assert!(looks_fingerprinted("style.3f9a1c.css"));
+        assert!(!looks_fingerprinted("style.css"));
+        assert!(!looks_fingerprinted("jquery.min.js"));
+    }
+
+    #[test]
+    fn fingerprint_and_remove_stale() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().to_str().unwrap();
+        let mut sd = StaticDir::new(out, true, false);
+        sd.add_bytes("style.css", b"old", None).unwrap();
+        sd.add_bytes("robots.txt", b"", None).unwrap();
+        let old_url = sd.url("style.css");
+        assert_ne!(old_url, "/static/style.css");
+        assert_eq!(sd.url("robots.txt"), "/static/robots.txt");
+        assert_eq!(sd.finish().unwrap(), (3, 0, 0));
+
+        let mut sd = StaticDir::new(out, true, false);
+        sd.add_bytes("style.css", b"new", None).unwrap();
+        sd.add_bytes("robots.txt", b"", None).unwrap();
+        assert_ne!(sd.url("style.css"), old_url);
+        let manifest_url = sd.url("style.css");
+        assert_eq!(sd.finish().unwrap(), (2, 1, 1));
+        assert!(!dir.path().join(&old_url["/static/".len()..]).exists());
+        let manifest = fs::read_to_string(dir.path().join("manifest.json")).unwrap();
+        assert!(manifest.contains(&manifest_url["/static/".len()..]));
+    }
+
+    #[test]
+    fn links_to_fingerprinted() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        let build = dir.path().join("build");
+        fs::create_dir_all(src.join("img")).unwrap();
+        fs::create_dir_all(src.join("css")).unwrap();
+        fs::write(src.join("img/photo.png"), b"png").unwrap();
+        fs::write(src.join("robots.txt"), b"").unwrap();
+        fs::write(
+            src.join("css/site.css"),
+            "a { background: url(../img/photo.png) }\n\
+             b { background: url('/static/img/photo.png?v=1') }\n\
+             i { background: url(data:image/png;base64,AA==) }",
+        )
+        .unwrap();
+        let mut sd = StaticDir::new(build.join("static").to_str().unwrap(), true, false);
+        sd.add_dir(src.to_str().unwrap()).unwrap();
+        sd.finish().unwrap();
+
+        let html = sd.rewrite(
+            "<link href='/static/css/site.css' rel='stylesheet'>\
+             <img src='/static/img/photo.png' srcset='/static/img/photo.png 2x'>\
+             <a href='/static/robots.txt'>robots</a>",
+        );
+        assert!(!html.contains("/static/img/photo.png"));
+        assert!(html.contains("href='/static/robots.txt'"));
+        fs::write(build.join("index.html"), &html).unwrap();
+        let report = check_links(&build, &SiteUrl::default(), &HashMap::new());
+        assert_eq!(report.broken.len(), 0, "{:?}", report.broken);
+
+        // Stylesheets link to the new names too, however they spell the link
+        let photo = sd.url("img/photo.png");
+        let name = &photo[photo.rfind('/').unwrap() + 1..];
+        let css = fs::read_to_string(build.join(&sd.url("css/site.css")[1..])).unwrap();
+        assert!(css.contains(&format!("url(../img/{})", name)));
+        assert!(css.contains(&format!("url('{}?v=1')", photo)));
+        assert!(css.contains("url(data:image/png;base64,AA==)"));
+
+        // Without fingerprinting, nothing needs to change
+        let sd = StaticDir::new(build.join("static").to_str().unwrap(), false, false);
+        assert_eq!(
+            sd.rewrite("<img src='/static/img/photo.png'>"),
+            "<img src='/static/img/photo.png'>"
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+
+use config::{Config, Value as ConfigValue};
+
+/// Someone who writes for the blog, from an `[authors.<key>]` table in the config
+#[derive(Debug, Clone, PartialEq)]
+pub struct Author {
+    /// Used in `Author` headers and in the URL of their page, `/authors/<key>/`
+    pub key: String,
+    pub name: String,
+    pub bio: String,
+    /// A URL, or a path in the static directory
+    pub avatar: Option<String>,
+    /// Pairs of link text and URL, like their own site or social media
+    pub links: Vec<(String, String)>,
+}
+
+/// Everyone in the `[authors]` config table, by key
+#[derive(Debug, Default)]
+pub struct Authors {
+    by_key: BTreeMap<String, Author>,
+}
+impl Authors {
+    /// Read the `[authors]` table from *conf*. Each author needs a `name`, and can have a `bio`,
+    /// an `avatar` and a list of `links` tables with a `name` and a `url`.
+    pub fn from_config(conf: &Config) -> Result<Self, String> {
+        let table = conf.get_table("authors").unwrap_or_default();
+        let mut by_key = BTreeMap::new();
+        for (key, value) in table {
+            let author =
+                parse_author(&key, value).map_err(|e| format!("authors.{}: {}", key, e))?;
+            by_key.insert(key, author);
+        }
+        Ok(Authors { by_key })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_key.is_empty()
+    }
+
+    pub fn keys(&self) -> Vec<&str> {
+        self.by_key.keys().map(String::as_str).collect()
+    }
+
+    /// Every author, in order of their key
+    pub fn iter(&self) -> impl Iterator<Item = &Author> {
+        self.by_key.values()
+    }
+
+    /// The author that *s* from an `Author` header refers to, by key or by display name, ignoring
+    /// case either way so posts written before the author had an entry still find it
+    pub fn find(&self, s: &str) -> Option<&Author> {
+        let s = s.trim().to_lowercase();
+        self.by_key
+            .get(&s)
+            .or_else(|| self.by_key.values().find(|a| a.name.to_lowercase() == s))
+    }
+}
+
+fn parse_author(key: &str, value: ConfigValue) -> Result<Author, String> {
+    let mut table = value.into_table().map_err(|e| e.to_string())?;
+    let mut get_str = |k: &str| -> Result<Option<String>, String> {
+        match table.remove(k) {
+            Some(v) => v.into_str().map(Some).map_err(|e| format!("{}: {}", k, e)),
+            None => Ok(None),
+        }
+    };
+    let name = get_str("name")?.ok_or_else(|| "name is missing".to_string())?;
+    let bio = get_str("bio")?.unwrap_or_default();
+    let avatar = get_str("avatar")?.filter(|a| !a.is_empty());
+    let mut links = vec![];
+    if let Some(v) = table.remove("links") {
+        for link in v.into_array().map_err(|e| format!("links: {}", e))? {
+            let mut link = link.into_table().map_err(|e| format!("links: {}", e))?;
+            let mut get = |k: &str| {
+                link.remove(k)
+                    .ok_or_else(|| format!("links: {} is missing", k))
+                    .and_then(|v| v.into_str().map_err(|e| format!("links: {}", e)))
+            };
+            links.push((get("name")?, get("url")?));
+        }
+    }
+    Ok(Author {
+        key: key.to_string(),
+        name,
+        bio,
+        avatar,
+        links,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Authors;
+    use config::{Config, File as ConfigFile, FileFormat};
+
+    #[test]
+    fn from_config() {
+        let mut conf = Config::new();
+        conf.merge(ConfigFile::from_str(
+            "[authors.jdoe]
+name = \"John Doe\"
+bio = \"Writes about cats.\"
+links = [{ name = \"GitHub\", url = \"https://github.com/jdoe\" }]
+
+[authors.ann]
+name = \"Ann\"
+",
+            FileFormat::Toml,
+        ))
+        .unwrap();
+        let authors = Authors::from_config(&conf).unwrap();
+        assert_eq!(authors.keys(), vec!["ann", "jdoe"]);
+        let jdoe = authors.find("John DOE").unwrap();
+        assert_eq!(jdoe.key, "jdoe");
+        assert_eq!(
+            jdoe.links,
+            vec![("GitHub".to_string(), "https://github.com/jdoe".to_string())]
+        );
+        assert_eq!(authors.find(" Ann ").unwrap().avatar, None);
+        assert!(authors.find("nobody").is_none());
+
+        let mut conf = Config::new();
+        conf.merge(ConfigFile::from_str(
+            "[authors.x]\nbio = \"?\"",
+            FileFormat::Toml,
+        ))
+        .unwrap();
+        assert_eq!(
+            Authors::from_config(&conf).unwrap_err(),
+            "authors.x: name is missing"
+        );
+        assert!(Authors::from_config(&Config::new()).unwrap().is_empty());
+    }
+}
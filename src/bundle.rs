@@ -0,0 +1,142 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::post::file::BUNDLE_INDEX_FNAME;
+use crate::util::fs::recursive_find_files;
+use crate::util::html::rewrite_attrs;
+
+/// Attributes that can point at a file in a bundle
+const URL_ATTRS: &[&str] = &["src", "href", "poster"];
+
+/// The files in the bundle directory *dname* that get copied next to the rendered post, as paths
+/// relative to *dname* using `/` paired with where they are. Posts are left out, and so is
+/// anything in another bundle nested inside this one.
+pub fn find_assets(dname: &Path) -> Vec<(String, PathBuf)> {
+    let mut out = vec![];
+    for fname in recursive_find_files(dname.to_str().unwrap()) {
+        if fname.extension() == Some("reb".as_ref()) {
+            continue;
+        }
+        let nested = fname
+            .ancestors()
+            .skip(1)
+            .take_while(|d| *d != dname)
+            .any(|d| d.join(BUNDLE_INDEX_FNAME).is_file());
+        if nested {
+            continue;
+        }
+        let rel = fname
+            .strip_prefix(dname)
+            .unwrap()
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect::<Vec<String>>()
+            .join("/");
+        out.push((rel, fname));
+    }
+    out.sort();
+    out
+}
+
+/// Decode `%XX` escapes in *s*, leaving anything that isn't a valid escape alone
+fn percent_decode(s: &str) -> String {
+    let b = s.as_bytes();
+    let mut out = Vec::with_capacity(b.len());
+    let mut i = 0;
+    while i < b.len() {
+        if b[i] == b'%' {
+            if let Some(byte) = s
+                .get(i + 1..i + 3)
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+            {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(b[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// If *url* is relative and points at one of *assets*, the URL it should have once the assets are
+/// at *base_url*
+fn resolve(url: &str, assets: &HashSet<&str>, base_url: &str) -> Option<String> {
+    if url.is_empty() || url.starts_with('/') || url.starts_with('#') || url.starts_with('?') {
+        return None;
+    }
+    // Anything with a scheme, like https: or mailto:
+    match (url.find(':'), url.find('/')) {
+        (Some(colon), Some(slash)) if colon < slash => return None,
+        (Some(_), None) => return None,
+        _ => {}
+    }
+    let split = url.find(['?', '#']).unwrap_or(url.len());
+    let (mut path, suffix) = url.split_at(split);
+    while path.starts_with("./") {
+        path = &path[2..];
+    }
+    if assets.contains(percent_decode(path).as_str()) {
+        Some(format!("{}/{}{}", base_url, path, suffix))
+    } else {
+        None
+    }
+}
+
+/// Point relative links in the rendered post *html* that refer to one of *assets* at where the
+/// assets are copied to, *base_url*, so they work from any page the post is shown on
+pub fn rewrite_links(html: &str, assets: &[(String, PathBuf)], base_url: &str) -> String {
+    let assets: HashSet<&str> = assets.iter().map(|(rel, _)| rel.as_str()).collect();
+    rewrite_attrs(html, |_, attr, value| {
+        if URL_ATTRS.contains(&attr) {
+            resolve(value, &assets, base_url)
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_assets, percent_decode, rewrite_links};
+    use std::fs;
+    use std::path::PathBuf;
+
+    #[test]
+    fn assets() {
+        let dir = tempfile::tempdir().unwrap();
+        let d = dir.path();
+        fs::create_dir_all(d.join("img")).unwrap();
+        fs::create_dir_all(d.join("nested")).unwrap();
+        for f in &[
+            "index.reb",
+            "a.png",
+            "img/b.jpg",
+            "nested/index.reb",
+            "nested/c.png",
+        ] {
+            fs::write(d.join(f), "").unwrap();
+        }
+        let rels: Vec<String> = find_assets(d).into_iter().map(|(rel, _)| rel).collect();
+        assert_eq!(rels, vec!["a.png", "img/b.jpg"]);
+    }
+
+    #[test]
+    fn rewrite() {
+        let assets = vec![
+            ("a.png".to_string(), PathBuf::new()),
+            ("my pic.jpg".to_string(), PathBuf::new()),
+            ("doc.pdf".to_string(), PathBuf::new()),
+        ];
+        let html = "<img src=\"a.png\"><img src='./my%20pic.jpg'><a href=\"doc.pdf#page=2\">\
+                    <a href=\"other.html\"><a href=\"/a.png\"><a href=\"https://x.com/a.png\">";
+        assert_eq!(
+            rewrite_links(html, &assets, "/posts/p-1"),
+            "<img src=\"/posts/p-1/a.png\"><img src=\"/posts/p-1/my%20pic.jpg\">\
+             <a href=\"/posts/p-1/doc.pdf#page=2\">\
+             <a href=\"other.html\"><a href=\"/a.png\"><a href=\"https://x.com/a.png\">"
+        );
+        assert_eq!(percent_decode("a%20b%zz%"), "a b%zz%");
+    }
+}
@@ -0,0 +1,614 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use config::Config;
+
+use crate::authors::Authors;
+use crate::post::file::{File as PostFile, FileOpts, Kind, KNOWN_HEADERS};
+use crate::post::PostParseError;
+use crate::series::find_series;
+use crate::util::fs::search_path;
+use crate::util::slug::{slugify, SlugOpts};
+
+#[derive(Debug, PartialEq)]
+pub enum Level {
+    Warning,
+    Error,
+}
+
+/// A single problem found by `reb check`, formatted as `path:line: level: message` so editors and
+/// pre-commit hooks can jump straight to it
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub path: PathBuf,
+    pub line: Option<usize>,
+    pub level: Level,
+    pub msg: String,
+}
+impl Diagnostic {
+    pub fn new(path: &Path, line: Option<usize>, level: Level, msg: String) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            line,
+            level,
+            msg,
+        }
+    }
+}
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let level = match self.level {
+            Level::Warning => "warning",
+            Level::Error => "error",
+        };
+        match self.line {
+            Some(line) => write!(
+                f,
+                "{}:{}: {}: {}",
+                self.path.display(),
+                line,
+                level,
+                self.msg
+            ),
+            None => write!(f, "{}: {}: {}", self.path.display(), level, self.msg),
+        }
+    }
+}
+
+/// Directories that reb writes its own pages to, so standalone pages can't go there
+const RESERVED_DIRS: &[&str] = &["posts", "static", "series", "authors", "search"];
+
+/// Parse the post at *path* with strict headers, returning a diagnostic if that isn't possible.
+/// With *page*, it's read as a standalone page.
+pub fn load_post(path: &Path, page: bool) -> Result<PostFile, Diagnostic> {
+    let opts = FileOpts {
+        strict_headers: true,
+        page,
+    };
+    PostFile::new_from_file(path, Some(opts)).map_err(|e| match e {
+        PostParseError::AtLine(line, inner) => {
+            Diagnostic::new(path, Some(line), Level::Error, inner.to_string())
+        }
+        e => Diagnostic::new(path, None, Level::Error, e.to_string()),
+    })
+}
+
+/// Problems that can be found by looking at a single post in isolation
+pub fn check_post(path: &Path, pf: &PostFile) -> Vec<Diagnostic> {
+    let mut out = vec![];
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for h in pf.headers() {
+        let key = h.key.to_lowercase();
+        if let Some(first) = seen.get(&key) {
+            out.push(Diagnostic::new(
+                path,
+                Some(h.line),
+                Level::Error,
+                format!(
+                    "duplicate header '{}', only the one on line {} is used",
+                    h.key, first
+                ),
+            ));
+            continue;
+        }
+        seen.insert(key.clone(), h.line);
+        if !KNOWN_HEADERS.contains(&key.as_str()) {
+            out.push(Diagnostic::new(
+                path,
+                Some(h.line),
+                Level::Warning,
+                format!("unknown header '{}'", h.key),
+            ));
+        }
+    }
+    if let Some(h) = pf.find_header("slug") {
+        let slug = slugify(&h.value.to_string());
+        if slug != h.value.to_string() {
+            out.push(Diagnostic::new(
+                path,
+                Some(h.line),
+                Level::Warning,
+                format!("slug '{}' will be used as '{}'", h.value, slug),
+            ));
+        }
+    }
+    out
+}
+
+/// Problems that only show up when looking at all posts together: duplicate IDs, posts that
+/// would be rendered to the same output file, and pages in the way of reb's own pages
+pub fn check_post_set(posts: &[(PathBuf, PostFile)], slug_opts: &SlugOpts) -> Vec<Diagnostic> {
+    let mut out = vec![];
+    let mut ids: HashMap<String, &Path> = HashMap::new();
+    let mut fnames: HashMap<String, &Path> = HashMap::new();
+    for (path, pf) in posts {
+        if let Some(h) = pf.find_header("id") {
+            let id = h.value.to_string();
+            if let Some(other) = ids.get(&id) {
+                out.push(Diagnostic::new(
+                    path,
+                    Some(h.line),
+                    Level::Error,
+                    format!("ID '{}' is already used by {}", id, other.display()),
+                ));
+            } else {
+                ids.insert(id, path);
+            }
+        }
+        let fname = match pf.kind() {
+            Kind::Post => pf.get_long_rendered_filename(slug_opts),
+            Kind::Page => {
+                let page_path = pf.page_path(slug_opts);
+                let first = page_path.trim_start_matches('/').split('/').next().unwrap();
+                if first.is_empty() || first == "index.html" || RESERVED_DIRS.contains(&first) {
+                    out.push(Diagnostic::new(
+                        path,
+                        pf.find_header("path").map(|h| h.line),
+                        Level::Error,
+                        format!("page path '{}' is where reb puts its own pages", page_path),
+                    ));
+                }
+                page_path
+            }
+        };
+        if let Some(other) = fnames.get(&fname) {
+            out.push(Diagnostic::new(
+                path,
+                None,
+                Level::Error,
+                format!(
+                    "output file '{}' would also be written by {}",
+                    fname,
+                    other.display()
+                ),
+            ));
+        } else {
+            fnames.insert(fname, path);
+        }
+    }
+    out
+}
+
+/// Problems with series of posts: part numbers that are missing or used twice, and parts that
+/// don't say where they go when others in the same series do
+pub fn check_series(posts: &[(PathBuf, PostFile)], default_lang: &str) -> Vec<Diagnostic> {
+    let mut out = vec![];
+    for (path, pf) in posts.iter().filter(|(_, pf)| pf.kind() == Kind::Post) {
+        if let (Some(h), None) = (pf.find_header("series-part"), pf.series()) {
+            out.push(Diagnostic::new(
+                path,
+                Some(h.line),
+                Level::Warning,
+                "Series-Part is ignored without a Series header".to_string(),
+            ));
+        }
+    }
+    for series in find_series(posts.iter().map(|(_, pf)| pf), default_lang) {
+        let parts: Vec<&(PathBuf, PostFile)> = series.parts.iter().map(|i| &posts[*i]).collect();
+        let numbers: Vec<u32> = parts
+            .iter()
+            .filter_map(|(_, pf)| pf.series_part())
+            .collect();
+        if numbers.is_empty() {
+            continue;
+        }
+        let mut seen: HashMap<u32, &Path> = HashMap::new();
+        for (path, pf) in &parts {
+            let n = match pf.series_part() {
+                Some(n) => n,
+                None => {
+                    out.push(Diagnostic::new(
+                        path,
+                        None,
+                        Level::Warning,
+                        format!(
+                            "part of series '{}' without a Series-Part, so it goes last",
+                            series.name
+                        ),
+                    ));
+                    continue;
+                }
+            };
+            match seen.get(&n) {
+                Some(other) => out.push(Diagnostic::new(
+                    path,
+                    pf.find_header("series-part").map(|h| h.line),
+                    Level::Warning,
+                    format!(
+                        "part {} of series '{}' is also {}",
+                        n,
+                        series.name,
+                        other.display()
+                    ),
+                )),
+                None => {
+                    seen.insert(n, path);
+                }
+            }
+        }
+        let max = *numbers.iter().max().unwrap();
+        let missing: Vec<String> = (1..max)
+            .filter(|n| !seen.contains_key(n))
+            .map(|n| n.to_string())
+            .collect();
+        if !missing.is_empty() {
+            out.push(Diagnostic::new(
+                &parts[0].0,
+                None,
+                Level::Warning,
+                format!(
+                    "series '{}' has no part {}",
+                    series.name,
+                    missing.join(", ")
+                ),
+            ));
+        }
+    }
+    out
+}
+
+/// Translations of posts that don't exist, and more than one version of a post in the same
+/// language
+pub fn check_translations(posts: &[(PathBuf, PostFile)], default_lang: &str) -> Vec<Diagnostic> {
+    let mut out = vec![];
+    let ids: HashMap<String, &Path> = posts
+        .iter()
+        .map(|(path, pf)| (pf.id(), path.as_path()))
+        .collect();
+    let mut seen: HashMap<(String, String), &Path> = HashMap::new();
+    for (path, pf) in posts.iter().filter(|(_, pf)| pf.kind() == Kind::Post) {
+        let line = pf.find_header("translation-of").map(|h| h.line);
+        let original = match pf.translation_of() {
+            Some(id) => {
+                if !ids.contains_key(&id) {
+                    out.push(Diagnostic::new(
+                        path,
+                        line,
+                        Level::Warning,
+                        format!("translation of '{}', but no post has that ID", id),
+                    ));
+                }
+                id
+            }
+            None => pf.id(),
+        };
+        let lang = pf.lang().unwrap_or_else(|| default_lang.to_string());
+        let key = (original, lang);
+        match seen.get(&key) {
+            Some(other) => out.push(Diagnostic::new(
+                path,
+                line,
+                Level::Warning,
+                format!(
+                    "{} is already the '{}' version of post '{}'",
+                    other.display(),
+                    key.1,
+                    key.0
+                ),
+            )),
+            None => {
+                seen.insert(key, path);
+            }
+        }
+    }
+    out
+}
+
+/// Authors of posts who aren't in the `[authors]` config table, if there is one, since their
+/// names won't link to an author page
+pub fn check_authors(posts: &[(PathBuf, PostFile)], authors: &Authors) -> Vec<Diagnostic> {
+    let mut out = vec![];
+    if authors.is_empty() {
+        return out;
+    }
+    for (path, pf) in posts {
+        for name in pf.authors() {
+            if authors.find(&name).is_none() {
+                out.push(Diagnostic::new(
+                    path,
+                    pf.find_header("author").map(|h| h.line),
+                    Level::Warning,
+                    format!("author '{}' is not in the [authors] config table", name),
+                ));
+            }
+        }
+    }
+    out
+}
+
+/// The line in the TOML config *text* that sets *key*, like `paths.parse_bin`
+fn config_line(text: &str, key: &str) -> Option<usize> {
+    let (table, name) = match key.rfind('.') {
+        Some(i) => (&key[..i], &key[i + 1..]),
+        None => ("", key),
+    };
+    let mut current = "";
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            current = line.trim_matches(|c| c == '[' || c == ']').trim();
+        } else if current == table && line.split_once('=').is_some_and(|(k, _)| k.trim() == name) {
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+/// Problems with the paths in the config at *conf_fname*, as it is in the file. Nothing is made
+/// or looked up for it first, so this works on a blog that's never been built.
+pub fn check_config_paths(conf_fname: &Path, conf: &Config) -> Vec<Diagnostic> {
+    let text = read_to_string(conf_fname).unwrap_or_default();
+    let mut out = vec![];
+    let mut error = |key: &str, msg: String| {
+        out.push(Diagnostic::new(
+            conf_fname,
+            config_line(&text, key),
+            Level::Error,
+            msg,
+        ))
+    };
+    let dirs = ["paths.post_dname"];
+    let files = ["paths.blog_img_fname", "paths.favicon_fname"];
+    for key in dirs.iter() {
+        let value = conf.get_str(key).unwrap();
+        if !Path::new(&value).is_dir() {
+            error(key, format!("{} = {} is not a directory", key, value));
+        }
+    }
+    // Optional, but must be a directory if it's there
+    for key in ["paths.static_dname", "paths.pages_dname"].iter() {
+        let value = conf.get_str(key).unwrap();
+        if Path::new(&value).exists() && !Path::new(&value).is_dir() {
+            error(key, format!("{} = {} is not a directory", key, value));
+        }
+    }
+    for key in files.iter() {
+        let value = conf.get_str(key).unwrap();
+        if !Path::new(&value).is_file() {
+            error(key, format!("{} = {} is not a file", key, value));
+        }
+    }
+    // The parser is a file here or a program in $PATH, like build finds it
+    let key = "paths.parse_bin";
+    let value = conf.get_str(key).unwrap();
+    if !Path::new(&value).is_file() && search_path(Path::new(&value)).is_none() {
+        error(
+            key,
+            format!("{} = {} is not a file or a program in PATH", key, value),
+        );
+    }
+    // The build directory is made by build, but can't be a file
+    let key = "paths.build_dname";
+    let value = conf.get_str(key).unwrap();
+    if Path::new(&value).is_file() {
+        error(key, format!("{} = {} is not a directory", key, value));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_config_paths, check_post, check_post_set, check_series, check_translations,
+        config_line, Level,
+    };
+    use crate::post::file::File as PostFile;
+    use crate::util::slug::SlugOpts;
+    use config::{Config, File as ConfigFile, FileFormat};
+    use std::io::BufReader;
+    use std::path::{Path, PathBuf};
+
+    fn post(text: &'static str) -> PostFile {
+        let br = BufReader::new(text.as_bytes());
+        PostFile::new_from_buf(Box::new(br), None, None).unwrap()
+    }
+
+    const GOOD: &str = "Title: One Two Three
+Author: Me
+Date: Thu, 18 Oct 2018 10:00:00 +0000
+ID: abcd1234
+
+Body";
+
+    #[test]
+    fn good_post() {
+        assert!(check_post(Path::new("a.reb"), &post(GOOD)).is_empty());
+    }
+
+    #[test]
+    fn dup_and_unknown() {
+        let pf = post(
+            "Title: One Two Three
+Author: Me
+Date: Thu, 18 Oct 2018 10:00:00 +0000
+ID: abcd1234
+Title: Again
+Colour: blue
+
+Body",
+        );
+        let d = check_post(Path::new("a.reb"), &pf);
+        assert_eq!(d.len(), 2);
+        assert_eq!(d[0].line, Some(5));
+        assert_eq!(d[0].level, Level::Error);
+        assert_eq!(d[1].line, Some(6));
+        assert_eq!(d[1].level, Level::Warning);
+        assert_eq!(d[1].to_string().split(": ").next().unwrap(), "a.reb:6");
+    }
+
+    #[test]
+    fn duplicate_ids() {
+        let posts = vec![
+            (PathBuf::from("a.reb"), post(GOOD)),
+            (PathBuf::from("b.reb"), post(GOOD)),
+        ];
+        let opts = SlugOpts {
+            max_len: 50,
+            max_words: 6,
+        };
+        let d = check_post_set(&posts, &opts);
+        // Same ID and same output filename
+        assert_eq!(d.len(), 2);
+        assert!(d.iter().all(|d| d.path == Path::new("b.reb")));
+    }
+
+    #[test]
+    fn page_paths() {
+        let posts = vec![
+            (
+                PathBuf::from("about.reb"),
+                post("Title: About\nType: page\nPath: /about/\n\nBody"),
+            ),
+            (
+                PathBuf::from("me.reb"),
+                post("Title: Me\nType: page\nPath: /about/\n\nBody"),
+            ),
+            (
+                PathBuf::from("home.reb"),
+                post("Title: Home\nType: page\nPath: /\n\nBody"),
+            ),
+            (
+                PathBuf::from("s.reb"),
+                post("Title: S\nType: page\nPath: /static/s.html\n\nBody"),
+            ),
+        ];
+        let opts = SlugOpts {
+            max_len: 50,
+            max_words: 6,
+        };
+        let d: Vec<String> = check_post_set(&posts, &opts)
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(
+            d,
+            vec![
+                "me.reb: error: output file '/about/' would also be written by about.reb",
+                "home.reb:3: error: page path '/' is where reb puts its own pages",
+                "s.reb:3: error: page path '/static/s.html' is where reb puts its own pages",
+            ]
+        );
+    }
+
+    #[test]
+    fn series_parts() {
+        let part = |id: &str, n: &str| {
+            let text = format!(
+                "Title: T\nAuthor: A\nDate: 2020-01-01\nID: {}\nSeries: S\n{}\n\nBody",
+                id, n
+            );
+            let br = BufReader::new(std::io::Cursor::new(text.into_bytes()));
+            PostFile::new_from_buf(Box::new(br), None, None).unwrap()
+        };
+        let posts = vec![
+            (PathBuf::from("a.reb"), part("a", "Series-Part: 1")),
+            (PathBuf::from("b.reb"), part("b", "Series-Part: 4")),
+            (PathBuf::from("c.reb"), part("c", "Series-Part: 4")),
+            (PathBuf::from("d.reb"), part("d", "")),
+            (PathBuf::from("e.reb"), post(GOOD)),
+        ];
+        let d: Vec<String> = check_series(&posts, "en")
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(
+            d,
+            vec![
+                "c.reb:6: warning: part 4 of series 'S' is also b.reb",
+                "d.reb: warning: part of series 'S' without a Series-Part, so it goes last",
+                "a.reb: warning: series 'S' has no part 2, 3",
+            ]
+        );
+    }
+
+    #[test]
+    fn translations() {
+        let version = |id: &str, headers: &str| {
+            let text = format!(
+                "Title: T\nAuthor: A\nDate: 2020-01-01\nID: {}\n{}\n\nBody",
+                id, headers
+            );
+            let br = BufReader::new(std::io::Cursor::new(text.into_bytes()));
+            PostFile::new_from_buf(Box::new(br), None, None).unwrap()
+        };
+        let posts = vec![
+            (PathBuf::from("a.reb"), version("a", "")),
+            (
+                PathBuf::from("a.de.reb"),
+                version("a-de", "Lang: de\nTranslation-Of: a"),
+            ),
+            (
+                PathBuf::from("a.en.reb"),
+                version("a-en", "Lang: en\nTranslation-Of: a"),
+            ),
+            (
+                PathBuf::from("b.de.reb"),
+                version("b-de", "Lang: de\nTranslation-Of: b"),
+            ),
+        ];
+        let d: Vec<String> = check_translations(&posts, "en")
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(
+            d,
+            vec![
+                "a.en.reb:6: warning: a.reb is already the 'en' version of post 'a'",
+                "b.de.reb:6: warning: translation of 'b', but no post has that ID",
+            ]
+        );
+    }
+
+    #[test]
+    fn config_paths() {
+        let text = "[strings]\nblog_title = \"A\"\n\n[paths]\n# parse_bin = \"old\"\nparse_bin = \"no-such-parser\"\n";
+        assert_eq!(config_line(text, "paths.parse_bin"), Some(6));
+        assert_eq!(config_line(text, "strings.blog_title"), Some(2));
+        assert_eq!(config_line(text, "paths.build_dname"), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let conf_fname = dir.path().join("config.toml");
+        let missing = dir.path().join("missing");
+        let text = format!(
+            "[paths]\npost_dname = \"{m}\"\npages_dname = \"{m}\"\nstatic_dname = \"{m}\"\n\
+             build_dname = \"{m}\"\nparse_bin = \"no-such-parser\"\n\
+             blog_img_fname = \"{m}\"\nfavicon_fname = \"{m}\"\n",
+            m = missing.display()
+        );
+        std::fs::write(&conf_fname, &text).unwrap();
+        let mut conf = Config::new();
+        conf.merge(ConfigFile::from_str(&text, FileFormat::Toml))
+            .unwrap();
+        let diags: Vec<(Option<usize>, String)> = check_config_paths(&conf_fname, &conf)
+            .into_iter()
+            .map(|d| (d.line, d.msg))
+            .collect();
+        let m = missing.display();
+        assert_eq!(
+            diags,
+            vec![
+                (
+                    Some(2),
+                    format!("paths.post_dname = {} is not a directory", m)
+                ),
+                (
+                    Some(7),
+                    format!("paths.blog_img_fname = {} is not a file", m)
+                ),
+                (
+                    Some(8),
+                    format!("paths.favicon_fname = {} is not a file", m)
+                ),
+                (
+                    Some(6),
+                    "paths.parse_bin = no-such-parser is not a file or a program in PATH"
+                        .to_string()
+                ),
+            ]
+        );
+        // Checking makes nothing
+        assert!(!missing.exists());
+    }
+}
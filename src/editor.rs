@@ -0,0 +1,167 @@
+use std::fs::{read_to_string, write};
+use std::path::Path;
+use std::process::Command;
+
+/// Lines reb adds to the top of a post to explain why it was opened again. They are removed before
+/// the post is parsed.
+const NOTE_PREFIX: &str = "# reb: ";
+
+/// Split an editor command like `code --wait` into words. Single and double quotes group words
+/// and a backslash escapes the next character, like in a shell.
+pub fn split_command(s: &str) -> Result<Vec<String>, String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => match chars.next() {
+                Some(c) => {
+                    word.push(c);
+                    in_word = true;
+                }
+                None => return Err(format!("Trailing backslash in '{}'", s)),
+            },
+            (Some(_), c) => word.push(c),
+            (None, '\'') | (None, '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(word.clone());
+                    word.clear();
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return Err(format!("Unclosed quote in '{}'", s));
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// Pick the editor command to use: *configured* if it isn't empty, then *visual* ($VISUAL), then
+/// *editor* ($EDITOR)
+pub fn resolve_editor(
+    configured: &str,
+    visual: Option<String>,
+    editor: Option<String>,
+) -> Result<Vec<String>, String> {
+    let candidates = vec![Some(configured.to_string()), visual, editor];
+    for cmd in candidates.into_iter().flatten() {
+        let words = split_command(&cmd)?;
+        if !words.is_empty() {
+            return Ok(words);
+        }
+    }
+    Err("No editor found. Set paths.editor_bin, $VISUAL or $EDITOR, or use --no-edit".to_string())
+}
+
+/// Remove the notes reb added to the top of the file
+fn strip_notes(text: &str) -> String {
+    let mut lines = text.split_inclusive('\n').peekable();
+    while let Some(l) = lines.peek() {
+        if !l.starts_with(NOTE_PREFIX) {
+            break;
+        }
+        lines.next();
+    }
+    lines.collect()
+}
+
+/// Open *text* in *editor* as the file *path* until *validate* accepts what comes back, adding the
+/// reason it didn't to the top of the file each time. Returns None if the user aborted by leaving
+/// the file unchanged or emptying it.
+pub fn edit_until_valid<T, F>(
+    editor: &[String],
+    path: &Path,
+    text: &str,
+    validate: F,
+) -> Result<Option<T>, String>
+where
+    F: Fn(&str) -> Result<T, String>,
+{
+    let mut current = text.to_string();
+    loop {
+        write(path, &current).map_err(|e| e.to_string())?;
+        let status = Command::new(&editor[0])
+            .args(&editor[1..])
+            .arg(path)
+            .status()
+            .map_err(|e| format!("Failed to run editor {}: {}", editor[0], e))?;
+        if !status.success() {
+            return Err(format!("Editor {} exited with {}", editor[0], status));
+        }
+        let edited = read_to_string(path).map_err(|e| e.to_string())?;
+        if edited == current || strip_notes(&edited).trim().is_empty() {
+            return Ok(None);
+        }
+        let edited = strip_notes(&edited);
+        match validate(&edited) {
+            Ok(v) => return Ok(Some(v)),
+            Err(e) => {
+                warn!("{}", e);
+                current = String::new();
+                for line in e.lines() {
+                    current += &format!("{}ERROR: {}\n", NOTE_PREFIX, line);
+                }
+                current += &format!(
+                    "{}Fix the problem and save, or quit without saving to abort\n",
+                    NOTE_PREFIX
+                );
+                current += &edited;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{resolve_editor, split_command, strip_notes};
+
+    #[test]
+    fn split() {
+        assert_eq!(
+            split_command("code --wait").unwrap(),
+            vec!["code", "--wait"]
+        );
+        assert_eq!(
+            split_command("  'my editor' -a \"b c\" d\\ e ''").unwrap(),
+            vec!["my editor", "-a", "b c", "d e", ""]
+        );
+        assert!(split_command("vim 'oops").is_err());
+        assert!(split_command("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn resolve() {
+        let s = |v: &str| Some(v.to_string());
+        assert_eq!(
+            resolve_editor("nano", s("vi"), s("ed")).unwrap(),
+            vec!["nano"]
+        );
+        assert_eq!(
+            resolve_editor("", s("code -w"), s("ed")).unwrap(),
+            vec!["code", "-w"]
+        );
+        assert_eq!(resolve_editor(" ", s(""), s("ed")).unwrap(), vec!["ed"]);
+        assert!(resolve_editor("", None, None).is_err());
+    }
+
+    #[test]
+    fn notes() {
+        let text = "# reb: ERROR: bad\n# reb: fix it\n# mine\nTitle: a\n";
+        assert_eq!(strip_notes(text), "# mine\nTitle: a\n");
+    }
+}
@@ -0,0 +1,99 @@
+use chrono::{DateTime, FixedOffset};
+
+use crate::util::html::escape;
+
+/// One post in a feed
+pub struct Entry {
+    /// Never changes for a post, unlike its URL
+    pub id: String,
+    pub title: String,
+    pub link: String,
+    pub authors: Vec<String>,
+    pub published: DateTime<FixedOffset>,
+    pub updated: DateTime<FixedOffset>,
+    /// The rendered post
+    pub content: String,
+}
+
+/// An Atom feed of *entries*, which should be newest first
+pub fn atom(
+    title: &str,
+    id: &str,
+    self_link: &str,
+    site_link: &str,
+    lang: &str,
+    entries: &[Entry],
+) -> String {
+    let updated = entries
+        .iter()
+        .map(|e| e.updated)
+        .max()
+        .map(|d| d.to_rfc3339())
+        .unwrap_or_default();
+    let mut s = String::new();
+    s += "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n";
+    s += &format!(
+        "<feed xmlns=\"http://www.w3.org/2005/Atom\" xml:lang=\"{}\">\n",
+        escape(lang)
+    );
+    s += &format!("  <title>{}</title>\n", escape(title));
+    s += &format!("  <id>{}</id>\n", escape(id));
+    s += &format!("  <link rel=\"self\" href=\"{}\" />\n", escape(self_link));
+    s += &format!("  <link href=\"{}\" />\n", escape(site_link));
+    s += &format!("  <updated>{}</updated>\n", updated);
+    s += "  <generator>reb</generator>\n";
+    for e in entries {
+        s += "  <entry>\n";
+        s += &format!("    <title>{}</title>\n", escape(&e.title));
+        s += &format!("    <id>{}</id>\n", escape(&e.id));
+        s += &format!("    <link href=\"{}\" />\n", escape(&e.link));
+        s += &format!("    <published>{}</published>\n", e.published.to_rfc3339());
+        s += &format!("    <updated>{}</updated>\n", e.updated.to_rfc3339());
+        for author in &e.authors {
+            s += &format!("    <author><name>{}</name></author>\n", escape(author));
+        }
+        s += &format!(
+            "    <content type=\"html\">{}</content>\n",
+            escape(&e.content)
+        );
+        s += "  </entry>\n";
+    }
+    s += "</feed>\n";
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{atom, Entry};
+    use chrono::DateTime;
+
+    #[test]
+    fn feed() {
+        let date = |s| DateTime::parse_from_rfc3339(s).unwrap();
+        let entries = vec![
+            Entry {
+                id: "urn:reb:post:b".to_string(),
+                title: "Fish & chips".to_string(),
+                link: "/posts/b.html".to_string(),
+                authors: vec!["Ann".to_string()],
+                published: date("2019-01-02T00:00:00+00:00"),
+                updated: date("2019-01-05T00:00:00+00:00"),
+                content: "<p>Hi</p>".to_string(),
+            },
+            Entry {
+                id: "urn:reb:post:a".to_string(),
+                title: "A".to_string(),
+                link: "/posts/a.html".to_string(),
+                authors: vec![],
+                published: date("2019-01-01T00:00:00+00:00"),
+                updated: date("2019-01-01T00:00:00+00:00"),
+                content: String::new(),
+            },
+        ];
+        let xml = atom("Blog", "urn:reb:feed:en", "/feed.xml", "/", "en", &entries);
+        assert!(xml.contains("<updated>2019-01-05T00:00:00+00:00</updated>\n  <generator>"));
+        assert!(xml.contains("<title>Fish &amp; chips</title>"));
+        assert!(xml.contains("<content type=\"html\">&lt;p&gt;Hi&lt;/p&gt;</content>"));
+        assert_eq!(xml.matches("<entry>").count(), 2);
+    }
+}
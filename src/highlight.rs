@@ -0,0 +1,896 @@
+use crate::util::html::{escape, tokenize, unescape, Tag, Token};
+
+/// Options for highlighting code blocks
+#[derive(Debug, Clone)]
+pub struct HighlightOpts {
+    pub enabled: bool,
+    /// Number lines unless a block says otherwise
+    pub line_numbers: bool,
+    /// Name of the color scheme, see `template::highlight_css`
+    pub theme: String,
+}
+
+/// What a language looks like, as far as coloring it goes
+struct Lang {
+    names: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+    /// Python style `"""` strings
+    triple_quotes: bool,
+    keywords: &'static [&'static str],
+    types: &'static [&'static str],
+    /// Constants like `true` and `None`
+    literals: &'static [&'static str],
+    /// Capitalized words are types
+    capitalized_types: bool,
+    /// `'a` is a lifetime, unless it's a char like `'a'`
+    lifetimes: bool,
+    /// `name!` is a macro and `#[...]` an attribute
+    rust_syntax: bool,
+    /// `@name` is a decorator or annotation
+    decorators: bool,
+    /// `$name` and `${...}` are variables
+    variables: bool,
+    /// The first word on a line followed by this is a key, like in TOML or YAML
+    key_sep: Option<char>,
+}
+
+const C_KEYWORDS: &[&str] = &[
+    "break", "case", "const", "continue", "default", "do", "else", "enum", "extern", "for", "goto",
+    "if", "inline", "register", "return", "sizeof", "static", "struct", "switch", "typedef",
+    "union", "volatile", "while",
+];
+const C_TYPES: &[&str] = &[
+    "bool", "char", "double", "float", "int", "long", "short", "signed", "size_t", "unsigned",
+    "void", "int8_t", "int16_t", "int32_t", "int64_t", "uint8_t", "uint16_t", "uint32_t",
+    "uint64_t",
+];
+
+const LANGS: &[Lang] = &[
+    Lang {
+        names: &["rust", "rs"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"'],
+        triple_quotes: false,
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+            "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "type",
+            "union", "unsafe", "use", "where", "while",
+        ],
+        types: &[
+            "bool", "char", "f32", "f64", "i8", "i16", "i32", "i64", "i128", "isize", "str", "u8",
+            "u16", "u32", "u64", "u128", "usize", "Self",
+        ],
+        literals: &["true", "false", "None", "Some", "Ok", "Err"],
+        capitalized_types: true,
+        lifetimes: true,
+        rust_syntax: true,
+        decorators: false,
+        variables: false,
+        key_sep: None,
+    },
+    Lang {
+        names: &["c", "h"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        triple_quotes: false,
+        keywords: C_KEYWORDS,
+        types: C_TYPES,
+        literals: &["NULL", "true", "false"],
+        capitalized_types: false,
+        lifetimes: false,
+        rust_syntax: false,
+        decorators: false,
+        variables: false,
+        key_sep: None,
+    },
+    Lang {
+        names: &["cpp", "c++", "cc", "cxx", "hpp"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        triple_quotes: false,
+        keywords: &[
+            "auto",
+            "break",
+            "case",
+            "catch",
+            "class",
+            "const",
+            "constexpr",
+            "continue",
+            "default",
+            "delete",
+            "do",
+            "else",
+            "enum",
+            "explicit",
+            "extern",
+            "for",
+            "friend",
+            "if",
+            "inline",
+            "namespace",
+            "new",
+            "noexcept",
+            "operator",
+            "override",
+            "private",
+            "protected",
+            "public",
+            "return",
+            "sizeof",
+            "static",
+            "struct",
+            "switch",
+            "template",
+            "this",
+            "throw",
+            "try",
+            "typedef",
+            "typename",
+            "union",
+            "using",
+            "virtual",
+            "volatile",
+            "while",
+        ],
+        types: C_TYPES,
+        literals: &["nullptr", "NULL", "true", "false"],
+        capitalized_types: false,
+        lifetimes: false,
+        rust_syntax: false,
+        decorators: false,
+        variables: false,
+        key_sep: None,
+    },
+    Lang {
+        names: &["go", "golang"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+        triple_quotes: false,
+        keywords: &[
+            "break",
+            "case",
+            "chan",
+            "const",
+            "continue",
+            "default",
+            "defer",
+            "else",
+            "fallthrough",
+            "for",
+            "func",
+            "go",
+            "goto",
+            "if",
+            "import",
+            "interface",
+            "map",
+            "package",
+            "range",
+            "return",
+            "select",
+            "struct",
+            "switch",
+            "type",
+            "var",
+        ],
+        types: &[
+            "bool",
+            "byte",
+            "complex64",
+            "complex128",
+            "error",
+            "float32",
+            "float64",
+            "int",
+            "int8",
+            "int16",
+            "int32",
+            "int64",
+            "rune",
+            "string",
+            "uint",
+            "uint8",
+            "uint16",
+            "uint32",
+            "uint64",
+            "uintptr",
+        ],
+        literals: &["true", "false", "nil", "iota"],
+        capitalized_types: false,
+        lifetimes: false,
+        rust_syntax: false,
+        decorators: false,
+        variables: false,
+        key_sep: None,
+    },
+    Lang {
+        names: &["java", "kotlin", "kt"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        triple_quotes: false,
+        keywords: &[
+            "abstract",
+            "break",
+            "case",
+            "catch",
+            "class",
+            "continue",
+            "default",
+            "do",
+            "else",
+            "enum",
+            "extends",
+            "final",
+            "finally",
+            "for",
+            "fun",
+            "if",
+            "implements",
+            "import",
+            "instanceof",
+            "interface",
+            "new",
+            "override",
+            "package",
+            "private",
+            "protected",
+            "public",
+            "return",
+            "static",
+            "super",
+            "switch",
+            "this",
+            "throw",
+            "throws",
+            "try",
+            "val",
+            "var",
+            "void",
+            "when",
+            "while",
+        ],
+        types: &[
+            "boolean", "byte", "char", "double", "float", "int", "long", "short", "String",
+        ],
+        literals: &["true", "false", "null"],
+        capitalized_types: true,
+        lifetimes: false,
+        rust_syntax: false,
+        decorators: true,
+        variables: false,
+        key_sep: None,
+    },
+    Lang {
+        names: &["python", "py", "python3"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        triple_quotes: true,
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+            "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in",
+            "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+            "with", "yield",
+        ],
+        types: &[
+            "bool", "bytes", "dict", "float", "int", "list", "object", "set", "str", "tuple",
+        ],
+        literals: &["True", "False", "None", "self"],
+        capitalized_types: true,
+        lifetimes: false,
+        rust_syntax: false,
+        decorators: true,
+        variables: false,
+        key_sep: None,
+    },
+    Lang {
+        names: &["javascript", "js", "jsx", "mjs", "typescript", "ts", "tsx"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+        triple_quotes: false,
+        keywords: &[
+            "as",
+            "async",
+            "await",
+            "break",
+            "case",
+            "catch",
+            "class",
+            "const",
+            "continue",
+            "debugger",
+            "default",
+            "delete",
+            "do",
+            "else",
+            "enum",
+            "export",
+            "extends",
+            "finally",
+            "for",
+            "from",
+            "function",
+            "if",
+            "implements",
+            "import",
+            "in",
+            "instanceof",
+            "interface",
+            "let",
+            "new",
+            "of",
+            "private",
+            "public",
+            "readonly",
+            "return",
+            "static",
+            "super",
+            "switch",
+            "this",
+            "throw",
+            "try",
+            "type",
+            "typeof",
+            "var",
+            "void",
+            "while",
+            "yield",
+        ],
+        types: &[
+            "any", "boolean", "never", "number", "object", "string", "symbol", "unknown",
+        ],
+        literals: &["true", "false", "null", "undefined", "NaN", "Infinity"],
+        capitalized_types: true,
+        lifetimes: false,
+        rust_syntax: false,
+        decorators: true,
+        variables: false,
+        key_sep: None,
+    },
+    Lang {
+        names: &["shell", "sh", "bash", "zsh", "console"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        triple_quotes: false,
+        keywords: &[
+            "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+            "in", "local", "return", "then", "until", "while",
+        ],
+        types: &[],
+        literals: &["true", "false"],
+        capitalized_types: false,
+        lifetimes: false,
+        rust_syntax: false,
+        decorators: false,
+        variables: true,
+        key_sep: None,
+    },
+    Lang {
+        names: &["toml", "ini"],
+        line_comments: &["#", ";"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        triple_quotes: true,
+        keywords: &[],
+        types: &[],
+        literals: &["true", "false"],
+        capitalized_types: false,
+        lifetimes: false,
+        rust_syntax: false,
+        decorators: false,
+        variables: false,
+        key_sep: Some('='),
+    },
+    Lang {
+        names: &["yaml", "yml"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        triple_quotes: false,
+        keywords: &[],
+        types: &[],
+        literals: &["true", "false", "null", "yes", "no"],
+        capitalized_types: false,
+        lifetimes: false,
+        rust_syntax: false,
+        decorators: false,
+        variables: false,
+        key_sep: Some(':'),
+    },
+    Lang {
+        names: &["json"],
+        line_comments: &[],
+        block_comment: None,
+        quotes: &['"'],
+        triple_quotes: false,
+        keywords: &[],
+        types: &[],
+        literals: &["true", "false", "null"],
+        capitalized_types: false,
+        lifetimes: false,
+        rust_syntax: false,
+        decorators: false,
+        variables: false,
+        key_sep: None,
+    },
+];
+
+fn find_lang(name: &str) -> Option<&'static Lang> {
+    let name = name.to_lowercase();
+    LANGS.iter().find(|l| l.names.contains(&name.as_str()))
+}
+
+/// Split *code* into pieces with the CSS class they should get, if any
+fn lex(lang: &Lang, code: &str) -> Vec<(Option<&'static str>, String)> {
+    let chars: Vec<char> = code.chars().collect();
+    let starts_with = |i: usize, s: &str| {
+        s.chars()
+            .enumerate()
+            .all(|(j, c)| chars.get(i + j) == Some(&c))
+    };
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let mut out: Vec<(Option<&'static str>, String)> = vec![];
+    let mut push = |class: Option<&'static str>, s: &[char]| {
+        let s: String = s.iter().collect();
+        match out.last_mut() {
+            Some((last, text)) if *last == class => *text += &s,
+            _ => out.push((class, s)),
+        }
+    };
+    let mut line_start = true;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        let class = if c == '\n' {
+            line_start = true;
+            push(None, &chars[i..=i]);
+            i += 1;
+            continue;
+        } else if c.is_whitespace() {
+            i += 1;
+            None
+        } else if let Some(lc) = lang.line_comments.iter().find(|lc| starts_with(i, lc)) {
+            i += lc.chars().count();
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            Some("hl-c")
+        } else if lang.block_comment.is_some_and(|(b, _)| starts_with(i, b)) {
+            let (b, e) = lang.block_comment.unwrap();
+            i += b.chars().count();
+            while i < chars.len() && !starts_with(i, e) {
+                i += 1;
+            }
+            i = (i + e.chars().count()).min(chars.len());
+            Some("hl-c")
+        } else if lang.quotes.contains(&c) {
+            let triple = lang.triple_quotes && starts_with(i, &c.to_string().repeat(3));
+            let delim_len = if triple { 3 } else { 1 };
+            i += delim_len;
+            while i < chars.len() {
+                if chars[i] == '\\' {
+                    i += 2;
+                } else if chars[i] == c && (!triple || starts_with(i, &c.to_string().repeat(3))) {
+                    i += delim_len;
+                    break;
+                } else {
+                    i += 1;
+                }
+            }
+            i = i.min(chars.len());
+            Some("hl-s")
+        } else if lang.lifetimes && c == '\'' {
+            // A char like 'a' or '\n', or else a lifetime like 'a
+            if chars.get(i + 1) == Some(&'\\') {
+                i += 2;
+                while i < chars.len() && chars[i] != '\'' && chars[i] != '\n' {
+                    i += 1;
+                }
+                i = (i + 1).min(chars.len());
+                Some("hl-s")
+            } else if chars.get(i + 2) == Some(&'\'') {
+                i += 3;
+                Some("hl-s")
+            } else {
+                i += 1;
+                while i < chars.len() && is_ident(chars[i]) {
+                    i += 1;
+                }
+                Some("hl-a")
+            }
+        } else if c.is_ascii_digit()
+            || (c == '.'
+                && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit())
+                && (i == 0 || !(is_ident(chars[i - 1]) || chars[i - 1] == '.')))
+        {
+            while i < chars.len() && (is_ident(chars[i]) || chars[i] == '.') {
+                // Don't eat ranges like 0..10 or method calls like 1.max(2)
+                if chars[i] == '.' && !chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()) {
+                    break;
+                }
+                i += 1;
+            }
+            Some("hl-n")
+        } else if lang.rust_syntax && c == '#' && (starts_with(i, "#[") || starts_with(i, "#![")) {
+            while i < chars.len() && chars[i] != ']' && chars[i] != '\n' {
+                i += 1;
+            }
+            i = (i + 1).min(chars.len());
+            Some("hl-a")
+        } else if lang.decorators && c == '@' {
+            i += 1;
+            while i < chars.len() && (is_ident(chars[i]) || chars[i] == '.') {
+                i += 1;
+            }
+            Some("hl-a")
+        } else if lang.variables && c == '$' {
+            i += 1;
+            if chars.get(i) == Some(&'{') {
+                while i < chars.len() && chars[i] != '}' && chars[i] != '\n' {
+                    i += 1;
+                }
+                i = (i + 1).min(chars.len());
+            } else {
+                while i < chars.len() && (is_ident(chars[i]) || chars[i] == '?' || chars[i] == '#')
+                {
+                    i += 1;
+                    if !is_ident(chars[i - 1]) {
+                        break;
+                    }
+                }
+            }
+            Some("hl-v")
+        } else if is_ident(c) {
+            while i < chars.len()
+                && (is_ident(chars[i]) || (lang.key_sep.is_some() && chars[i] == '-'))
+            {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            let mut next = i;
+            while next < chars.len() && (chars[next] == ' ' || chars[next] == '\t') {
+                next += 1;
+            }
+            let is_key =
+                line_start && lang.key_sep.is_some() && chars.get(next) == lang.key_sep.as_ref();
+            if is_key || lang.keywords.contains(&word.as_str()) {
+                Some("hl-k")
+            } else if lang.literals.contains(&word.as_str()) {
+                Some("hl-b")
+            } else if lang.types.contains(&word.as_str())
+                || (lang.capitalized_types && c.is_uppercase())
+            {
+                Some("hl-t")
+            } else if lang.rust_syntax
+                && chars.get(i) == Some(&'!')
+                && chars.get(i + 1) != Some(&'=')
+            {
+                i += 1;
+                Some("hl-m")
+            } else if chars.get(next) == Some(&'(') {
+                Some("hl-f")
+            } else {
+                None
+            }
+        } else {
+            i += 1;
+            None
+        };
+        if !c.is_whitespace() {
+            line_start = false;
+        }
+        push(class, &chars[start..i]);
+    }
+    out
+}
+
+/// What a code block's language class says, like `rust{linenos,2,4-6}`
+#[derive(Debug, PartialEq)]
+struct BlockOpts {
+    lang: String,
+    line_numbers: Option<bool>,
+    /// Ranges of lines to highlight, 1-based and inclusive
+    hl_lines: Vec<(usize, usize)>,
+}
+impl BlockOpts {
+    fn highlights(&self, line: usize) -> bool {
+        self.hl_lines.iter().any(|&(a, b)| a <= line && line <= b)
+    }
+}
+
+fn parse_block_opts(s: &str) -> BlockOpts {
+    let (lang, rest) = match s.find('{') {
+        Some(idx) => (&s[..idx], s[idx + 1..].trim_end_matches('}')),
+        None => (s, ""),
+    };
+    let mut opts = BlockOpts {
+        lang: lang.to_string(),
+        line_numbers: None,
+        hl_lines: vec![],
+    };
+    for item in rest.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+        match item {
+            "linenos" => opts.line_numbers = Some(true),
+            "nolinenos" => opts.line_numbers = Some(false),
+            _ => {
+                let mut parts = item.splitn(2, '-').map(|p| p.trim().parse::<usize>());
+                match (parts.next(), parts.next()) {
+                    (Some(Ok(a)), None) => opts.hl_lines.push((a, a)),
+                    (Some(Ok(a)), Some(Ok(b))) if a > b => {
+                        warn!(
+                            "Ignoring code block line range '{}' that ends before it starts",
+                            item
+                        )
+                    }
+                    (Some(Ok(a)), Some(Ok(b))) => opts.hl_lines.push((a, b)),
+                    _ => warn!("Ignoring unknown code block option '{}'", item),
+                }
+            }
+        }
+    }
+    opts
+}
+
+/// Turn the (unescaped) *code* into HTML
+fn render_code(code: &str, block: &BlockOpts, opts: &HighlightOpts) -> String {
+    let pieces = match find_lang(&block.lang) {
+        Some(lang) => lex(lang, code),
+        None => vec![(None, code.to_string())],
+    };
+    let render = |class: Option<&str>, text: &str| match class {
+        Some(class) if !text.is_empty() => {
+            format!("<span class=\"{}\">{}</span>", class, escape(text))
+        }
+        _ => escape(text),
+    };
+    let line_numbers = block.line_numbers.unwrap_or(opts.line_numbers);
+    if !line_numbers && block.hl_lines.is_empty() {
+        return pieces.iter().map(|(c, t)| render(*c, t)).collect();
+    }
+    // Every line gets its own span, so pieces that cross lines are split up
+    let mut lines: Vec<String> = vec![String::new()];
+    for (class, text) in &pieces {
+        for (idx, part) in text.split('\n').enumerate() {
+            if idx > 0 {
+                lines.push(String::new());
+            }
+            *lines.last_mut().unwrap() += &render(*class, part);
+        }
+    }
+    // The newline at the end of the last line doesn't start another
+    if code.ends_with('\n') {
+        lines.pop();
+    }
+    let width = lines.len().to_string().len();
+    let mut out = String::new();
+    for (idx, line) in lines.iter().enumerate() {
+        let num = idx + 1;
+        out += if block.highlights(num) {
+            "<span class=\"line hl\">"
+        } else {
+            "<span class=\"line\">"
+        };
+        if line_numbers {
+            out += &format!("<span class=\"ln\">{:>w$}</span>", num, w = width);
+        }
+        out += line;
+        out += "</span>\n";
+    }
+    out
+}
+
+/// Highlight the `<pre><code class="language-...">` blocks in the rendered post *html*
+pub fn highlight_code_blocks(html: &str, opts: &HighlightOpts) -> String {
+    if !opts.enabled {
+        return html.to_string();
+    }
+    let tokens = tokenize(html);
+    let mut out = String::with_capacity(html.len() * 2);
+    let mut i = 0;
+    while i < tokens.len() {
+        let (pre, code, lang_class) = match (&tokens[i], tokens.get(i + 1)) {
+            (Token::Tag(pre, _), Some(Token::Tag(code, _)))
+                if pre.name == "pre" && !pre.closing && code.name == "code" && !code.closing =>
+            {
+                let lang_class = code.attr("class").and_then(|c| {
+                    c.split_whitespace()
+                        .find(|c| c.starts_with("language-"))
+                        .map(|c| c["language-".len()..].to_string())
+                });
+                match lang_class {
+                    Some(lang_class) => (pre, code, lang_class),
+                    None => {
+                        out += tokens[i].source();
+                        i += 1;
+                        continue;
+                    }
+                }
+            }
+            (t, _) => {
+                out += t.source();
+                i += 1;
+                continue;
+            }
+        };
+        let end = match tokens[i + 2..]
+            .iter()
+            .position(|t| matches!(t, Token::Tag(tag, _) if tag.name == "code" && tag.closing))
+        {
+            Some(e) => i + 2 + e,
+            None => {
+                out += tokens[i].source();
+                i += 1;
+                continue;
+            }
+        };
+        let code_text: String = tokens[i + 2..end].iter().map(|t| t.source()).collect();
+        let block = parse_block_opts(&lang_class);
+        let mut pre: Tag = pre.clone();
+        let classes = match pre.attr("class") {
+            Some(c) => format!("{} highlight", c),
+            None => "highlight".to_string(),
+        };
+        pre.set_attr("class", &classes);
+        let mut code: Tag = code.clone();
+        let code_classes: Vec<String> = code
+            .attr("class")
+            .unwrap_or("")
+            .split_whitespace()
+            .map(|c| {
+                if c.starts_with("language-") {
+                    format!("language-{}", block.lang)
+                } else {
+                    c.to_string()
+                }
+            })
+            .collect();
+        code.set_attr("class", &code_classes.join(" "));
+        out += &pre.to_string();
+        out += &code.to_string();
+        out += &render_code(&unescape(&code_text), &block, opts);
+        i = end;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_lang, highlight_code_blocks, lex, parse_block_opts, HighlightOpts};
+
+    fn classes(lang: &str, code: &str) -> Vec<(Option<&'static str>, String)> {
+        lex(find_lang(lang).unwrap(), code)
+            .into_iter()
+            .filter(|(c, _)| c.is_some())
+            .collect()
+    }
+
+    fn c(class: &'static str, s: &str) -> (Option<&'static str>, String) {
+        (Some(class), s.to_string())
+    }
+
+    #[test]
+    fn rust() {
+        assert_eq!(
+            classes(
+                "rust",
+                "#[derive(Debug)]\nfn f<'a>(x: &'a str) -> u32 { println!(\"{}\", 'x'); 0x1f } // hi"
+            ),
+            vec![
+                c("hl-a", "#[derive(Debug)]"),
+                c("hl-k", "fn"),
+                c("hl-a", "'a"),
+                c("hl-a", "'a"),
+                c("hl-t", "str"),
+                c("hl-t", "u32"),
+                c("hl-m", "println!"),
+                c("hl-s", "\"{}\""),
+                c("hl-s", "'x'"),
+                c("hl-n", "0x1f"),
+                c("hl-c", "// hi"),
+            ]
+        );
+        assert_eq!(
+            classes("rust", "0..10"),
+            vec![c("hl-n", "0"), c("hl-n", "10")]
+        );
+    }
+
+    #[test]
+    fn other_langs() {
+        assert_eq!(
+            classes("python", "@dec\ndef f(s='''a\n'b'''): return None"),
+            vec![
+                c("hl-a", "@dec"),
+                c("hl-k", "def"),
+                c("hl-f", "f"),
+                c("hl-s", "'''a\n'b'''"),
+                c("hl-k", "return"),
+                c("hl-b", "None"),
+            ]
+        );
+        assert_eq!(
+            classes("toml", "[a]\nkey = \"v\" # c\nn = 1.5"),
+            vec![
+                c("hl-k", "key"),
+                c("hl-s", "\"v\""),
+                c("hl-c", "# c"),
+                c("hl-k", "n"),
+                c("hl-n", "1.5"),
+            ]
+        );
+        assert_eq!(
+            classes("sh", "echo \"$HOME\" ${X} $1"),
+            vec![c("hl-s", "\"$HOME\""), c("hl-v", "${X}"), c("hl-v", "$1")]
+        );
+        // The pieces always add back up to the code
+        let code = "let s = \"unterminated\n/* open";
+        let joined: String = lex(find_lang("rust").unwrap(), code)
+            .into_iter()
+            .map(|(_, s)| s)
+            .collect();
+        assert_eq!(joined, code);
+    }
+
+    #[test]
+    fn block_opts() {
+        let b = parse_block_opts("rust{linenos, 2,4-6}");
+        assert_eq!(b.lang, "rust");
+        assert_eq!(b.line_numbers, Some(true));
+        assert_eq!(b.hl_lines, vec![(2, 2), (4, 6)]);
+        assert!(b.highlights(5));
+        assert!(!b.highlights(3));
+        // Huge ranges don't cost anything, and backwards ones are left out
+        let b = parse_block_opts("rust{1-99999999999,9-3}");
+        assert_eq!(b.hl_lines, vec![(1, 99999999999)]);
+        assert!(b.highlights(7));
+        assert_eq!(parse_block_opts("go").line_numbers, None);
+    }
+
+    #[test]
+    fn blocks() {
+        let opts = HighlightOpts {
+            enabled: true,
+            line_numbers: false,
+            theme: "light".to_string(),
+        };
+        let html = "<p>x</p>\n<pre><code class=\"language-rust\">let x = &quot;a&lt;b&quot;;\n</code></pre>\n<pre><code>plain</code></pre>";
+        assert_eq!(
+            highlight_code_blocks(html, &opts),
+            "<p>x</p>\n<pre class=\"highlight\"><code class=\"language-rust\">\
+             <span class=\"hl-k\">let</span> x = <span class=\"hl-s\">&quot;a&lt;b&quot;</span>;\n\
+             </code></pre>\n<pre><code>plain</code></pre>"
+        );
+        let html = "<pre><code class=\"language-py{nolinenos,2}\">/* a\nb */\n</code></pre>";
+        assert_eq!(
+            highlight_code_blocks(html, &opts),
+            "<pre class=\"highlight\"><code class=\"language-py\">\
+             <span class=\"line\">/* a</span>\n<span class=\"line hl\">b */</span>\n</code></pre>"
+        );
+        let html = "<pre><code class=\"language-c{linenos}\">/* a\nb */\n</code></pre>";
+        assert_eq!(
+            highlight_code_blocks(html, &opts),
+            "<pre class=\"highlight\"><code class=\"language-c\">\
+             <span class=\"line\"><span class=\"ln\">1</span><span class=\"hl-c\">/* a</span></span>\n\
+             <span class=\"line\"><span class=\"ln\">2</span><span class=\"hl-c\">b */</span></span>\n\
+             </code></pre>"
+        );
+        let disabled = HighlightOpts {
+            enabled: false,
+            line_numbers: true,
+            theme: "light".to_string(),
+        };
+        assert_eq!(highlight_code_blocks(html, &disabled), html);
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, FixedOffset};
+use config::Config;
+
+/// Languages written right to left, by their primary subtag
+const RTL_LANGS: &[&str] = &[
+    "ar", "arc", "ckb", "dv", "fa", "he", "ks", "ps", "sd", "ug", "ur", "yi",
+];
+
+/// Words and formats used on pages in one language
+#[derive(Debug, Clone, PartialEq)]
+pub struct Strings {
+    /// The language code, like `en` or `pt-br`, as used in `<html lang>` and URLs
+    pub lang: String,
+    /// What the language calls itself, for links to translations
+    pub name: String,
+    /// `ltr` or `rtl`
+    pub dir: String,
+    /// A chrono format string, where `%B` is the month name from *months*
+    pub date_format: String,
+    pub months: Vec<String>,
+    /// Replace `strings.blog_title` and `strings.blog_subtitle` on pages in this language
+    pub blog_title: Option<String>,
+    pub blog_subtitle: Option<String>,
+    pub updated: String,
+    /// `{n}` is the number of minutes
+    pub min_read: String,
+    /// `{n}` is the number of words
+    pub words: String,
+    pub word: String,
+    pub and: String,
+    pub tags: String,
+    pub related_posts: String,
+    /// `{n}` is the part and `{total}` the number of parts
+    pub series_part: String,
+    /// `{n}` is the number of parts
+    pub series_count: String,
+    pub posts: String,
+    pub translations: String,
+    pub search: String,
+    pub no_results: String,
+    /// Shown on the search page to browsers without JavaScript
+    pub needs_javascript: String,
+    /// The link to the feed at the bottom of every page
+    pub feed: String,
+}
+impl Strings {
+    fn english(lang: &str) -> Self {
+        Strings {
+            lang: lang.to_string(),
+            name: "English".to_string(),
+            dir: "ltr".to_string(),
+            date_format: "%B %-d, %Y".to_string(),
+            months: [
+                "January",
+                "February",
+                "March",
+                "April",
+                "May",
+                "June",
+                "July",
+                "August",
+                "September",
+                "October",
+                "November",
+                "December",
+            ]
+            .iter()
+            .map(|m| m.to_string())
+            .collect(),
+            blog_title: None,
+            blog_subtitle: None,
+            updated: "updated".to_string(),
+            min_read: "{n} min read".to_string(),
+            words: "{n} words".to_string(),
+            word: "{n} word".to_string(),
+            and: "and".to_string(),
+            tags: "Tags".to_string(),
+            related_posts: "Related posts".to_string(),
+            series_part: "Part {n} of {total} in".to_string(),
+            series_count: "A series in {n} parts".to_string(),
+            posts: "Posts".to_string(),
+            translations: "Also in".to_string(),
+            search: "Search".to_string(),
+            no_results: "No posts found".to_string(),
+            needs_javascript: "Search needs JavaScript.".to_string(),
+            feed: "Feed".to_string(),
+        }
+    }
+
+    fn german(lang: &str) -> Self {
+        Strings {
+            lang: lang.to_string(),
+            name: "Deutsch".to_string(),
+            dir: "ltr".to_string(),
+            date_format: "%-d. %B %Y".to_string(),
+            months: [
+                "Januar",
+                "Februar",
+                "März",
+                "April",
+                "Mai",
+                "Juni",
+                "Juli",
+                "August",
+                "September",
+                "Oktober",
+                "November",
+                "Dezember",
+            ]
+            .iter()
+            .map(|m| m.to_string())
+            .collect(),
+            blog_title: None,
+            blog_subtitle: None,
+            updated: "aktualisiert".to_string(),
+            min_read: "{n} Min. Lesezeit".to_string(),
+            words: "{n} Wörter".to_string(),
+            word: "{n} Wort".to_string(),
+            and: "und".to_string(),
+            tags: "Schlagwörter".to_string(),
+            related_posts: "Ähnliche Beiträge".to_string(),
+            series_part: "Teil {n} von {total} in".to_string(),
+            series_count: "Eine Serie in {n} Teilen".to_string(),
+            posts: "Beiträge".to_string(),
+            translations: "Auch auf".to_string(),
+            search: "Suche".to_string(),
+            no_results: "Keine Beiträge gefunden".to_string(),
+            needs_javascript: "Die Suche braucht JavaScript.".to_string(),
+            feed: "Feed".to_string(),
+        }
+    }
+
+    /// The strings reb comes with for *lang*, which are English for languages it doesn't know
+    fn builtin(lang: &str) -> Self {
+        let primary = lang.split('-').next().unwrap_or(lang);
+        let mut s = match primary {
+            "de" => Strings::german(lang),
+            _ => Strings::english(lang),
+        };
+        if primary != "en" && primary != "de" {
+            s.name = lang.to_string();
+        }
+        if RTL_LANGS.contains(&primary) {
+            s.dir = "rtl".to_string();
+        }
+        s
+    }
+
+    /// *date* in this language
+    pub fn format_date(&self, date: &DateTime<FixedOffset>) -> String {
+        let month = &self.months[date.month0() as usize];
+        date.format(&self.date_format.replace("%B", month))
+            .to_string()
+    }
+}
+
+/// Replace `{name}` in *template* with each value
+pub fn fill(template: &str, values: &[(&str, usize)]) -> String {
+    let mut s = template.to_string();
+    for (name, value) in values {
+        s = s.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    s
+}
+
+/// The strings for every language the blog is written in
+#[derive(Debug)]
+pub struct I18n {
+    default_lang: String,
+    by_lang: BTreeMap<String, Strings>,
+}
+impl I18n {
+    /// Strings for the default language and each of *langs*, starting from the built-in ones and
+    /// replacing whatever a `[lang.strings.<code>]` config table gives
+    pub fn from_config(conf: &Config, langs: &[String]) -> Result<Self, String> {
+        let default_lang = conf.get_str("lang.default").unwrap().to_lowercase();
+        let tables = conf.get_table("lang.strings").unwrap_or_default();
+        let mut by_lang = BTreeMap::new();
+        for lang in langs.iter().chain(Some(&default_lang)) {
+            let mut s = Strings::builtin(lang);
+            if let Some(table) = tables.get(lang) {
+                let table = table
+                    .clone()
+                    .into_table()
+                    .map_err(|e| format!("lang.strings.{}: {}", lang, e))?;
+                for (key, value) in table {
+                    let err =
+                        |e: config::ConfigError| format!("lang.strings.{}.{}: {}", lang, key, e);
+                    if key == "months" {
+                        let months: Vec<String> = value
+                            .into_array()
+                            .map_err(err)?
+                            .into_iter()
+                            .map(|m| m.into_str().map_err(err))
+                            .collect::<Result<_, _>>()?;
+                        if months.len() != 12 {
+                            return Err(format!("lang.strings.{}.months: need 12 months", lang));
+                        }
+                        s.months = months;
+                        continue;
+                    }
+                    let value = value.into_str().map_err(err)?;
+                    let field = match key.as_str() {
+                        "name" => &mut s.name,
+                        "dir" => &mut s.dir,
+                        "date_format" => &mut s.date_format,
+                        "blog_title" => s.blog_title.get_or_insert_with(String::new),
+                        "blog_subtitle" => s.blog_subtitle.get_or_insert_with(String::new),
+                        "updated" => &mut s.updated,
+                        "min_read" => &mut s.min_read,
+                        "words" => &mut s.words,
+                        "word" => &mut s.word,
+                        "and" => &mut s.and,
+                        "tags" => &mut s.tags,
+                        "related_posts" => &mut s.related_posts,
+                        "series_part" => &mut s.series_part,
+                        "series_count" => &mut s.series_count,
+                        "posts" => &mut s.posts,
+                        "translations" => &mut s.translations,
+                        "search" => &mut s.search,
+                        "no_results" => &mut s.no_results,
+                        "needs_javascript" => &mut s.needs_javascript,
+                        "feed" => &mut s.feed,
+                        _ => {
+                            return Err(format!("lang.strings.{}: unknown string '{}'", lang, key))
+                        }
+                    };
+                    *field = value;
+                }
+            }
+            by_lang.insert(lang.clone(), s);
+        }
+        Ok(I18n {
+            default_lang,
+            by_lang,
+        })
+    }
+
+    /// The language of posts without a `Lang` header
+    pub fn default_lang(&self) -> &str {
+        &self.default_lang
+    }
+
+    /// Every language, the default one first and then in order of code
+    pub fn langs(&self) -> Vec<&str> {
+        let mut langs = vec![self.default_lang.as_str()];
+        langs.extend(
+            self.by_lang
+                .keys()
+                .map(String::as_str)
+                .filter(|l| *l != self.default_lang),
+        );
+        langs
+    }
+
+    /// The strings for *lang*, which must be the default language or one given to `from_config`
+    pub fn get(&self, lang: &str) -> &Strings {
+        &self.by_lang[lang]
+    }
+
+    /// Where pages in *lang* go under the root of the site: nowhere else for the default language,
+    /// and `/<lang>` for others
+    pub fn prefix(&self, lang: &str) -> String {
+        if lang == self.default_lang {
+            String::new()
+        } else {
+            format!("/{}", lang)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fill, I18n};
+    use chrono::DateTime;
+    use config::{Config, File as ConfigFile, FileFormat};
+
+    #[test]
+    fn strings() {
+        let mut conf = Config::new();
+        conf.merge(ConfigFile::from_str(
+            "[lang]
+default = \"en\"
+[lang.strings.de]
+related_posts = \"Mehr davon\"
+[lang.strings.fr]
+name = \"Français\"
+months = [\"janvier\", \"février\", \"mars\", \"avril\", \"mai\", \"juin\", \"juillet\",
+          \"août\", \"septembre\", \"octobre\", \"novembre\", \"décembre\"]
+date_format = \"%-d %B %Y\"
+",
+            FileFormat::Toml,
+        ))
+        .unwrap();
+        let langs = vec!["de".to_string(), "fr".to_string(), "ar".to_string()];
+        let i18n = I18n::from_config(&conf, &langs).unwrap();
+        assert_eq!(i18n.langs(), vec!["en", "ar", "de", "fr"]);
+        let date = DateTime::parse_from_rfc3339("2019-03-05T10:00:00+00:00").unwrap();
+        assert_eq!(i18n.get("en").format_date(&date), "March 5, 2019");
+        assert_eq!(i18n.get("de").format_date(&date), "5. März 2019");
+        assert_eq!(i18n.get("fr").format_date(&date), "5 mars 2019");
+        assert_eq!(i18n.get("de").related_posts, "Mehr davon");
+        assert_eq!(i18n.get("fr").name, "Français");
+        assert_eq!(i18n.get("ar").dir, "rtl");
+        assert_eq!(i18n.prefix("en"), "");
+        assert_eq!(i18n.prefix("de"), "/de");
+        assert_eq!(
+            fill(&i18n.get("de").series_part, &[("n", 2), ("total", 5)]),
+            "Teil 2 von 5 in"
+        );
+    }
+}
@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use image::{DynamicImage, FilterType, GenericImageView, ImageFormat, ImageOutputFormat};
+
+use crate::assets::split_ext;
+use crate::util::hash::fnv1a;
+use crate::util::html::rewrite_tags;
+
+/// How images are processed
+#[derive(Debug, Clone)]
+pub struct ImageOpts {
+    /// Widths to make smaller copies at. Images are never scaled up.
+    pub widths: Vec<u32>,
+    /// The `sizes` attribute for `<img>` tags that get a `srcset`
+    pub sizes: String,
+    /// Quality of resized JPEGs, from 1 to 100
+    pub jpeg_quality: u8,
+    /// Where resized copies and image sizes are kept between builds
+    pub cache_dname: PathBuf,
+}
+
+/// A smaller copy of an image
+pub struct Variant {
+    /// Where it goes, relative to the same directory as the original
+    pub rel: String,
+    pub width: u32,
+    pub bytes: Vec<u8>,
+}
+
+/// The result of processing one image
+pub struct Processed {
+    pub width: u32,
+    pub height: u32,
+    /// Smallest first
+    pub variants: Vec<Variant>,
+}
+
+/// The usual file extension for the image in *bytes*, if it's an image
+pub fn image_ext(bytes: &[u8]) -> Option<&'static str> {
+    match image::guess_format(bytes) {
+        Ok(ImageFormat::JPEG) => Some("jpg"),
+        Ok(ImageFormat::PNG) => Some("png"),
+        Ok(ImageFormat::GIF) => Some("gif"),
+        Ok(ImageFormat::WEBP) => Some("webp"),
+        Ok(ImageFormat::BMP) => Some("bmp"),
+        Ok(ImageFormat::ICO) => Some("ico"),
+        _ => None,
+    }
+}
+
+/// Where the copy of *rel* that is *width* pixels wide goes, like `img/cat-480w.jpg`
+fn variant_rel(rel: &str, width: u32) -> String {
+    match split_ext(rel) {
+        (stem, Some(ext)) => format!("{}-{}w.{}", stem, width, ext),
+        (stem, None) => format!("{}-{}w", stem, width),
+    }
+}
+
+/// Things that take long to work out about one image, kept in the cache directory
+struct Cache<'a> {
+    opts: &'a ImageOpts,
+    key: String,
+    bytes: &'a [u8],
+    decoded: Option<DynamicImage>,
+}
+impl<'a> Cache<'a> {
+    fn new(opts: &'a ImageOpts, bytes: &'a [u8]) -> Self {
+        Cache {
+            opts,
+            key: format!("{:016x}", fnv1a(bytes)),
+            bytes,
+            decoded: None,
+        }
+    }
+
+    fn decoded(&mut self) -> Result<&DynamicImage, String> {
+        if self.decoded.is_none() {
+            self.decoded = Some(image::load_from_memory(self.bytes).map_err(|e| e.to_string())?);
+        }
+        Ok(self.decoded.as_ref().unwrap())
+    }
+
+    /// Read *name* from the cache, or make it with *make* and save it there
+    fn get<F>(&mut self, name: &str, make: F) -> Result<Vec<u8>, String>
+    where
+        F: FnOnce(&DynamicImage) -> Result<Vec<u8>, String>,
+    {
+        let fname = self.opts.cache_dname.join(format!("{}-{}", self.key, name));
+        if let Ok(bytes) = fs::read(&fname) {
+            trace!("Using cached {}", fname.display());
+            return Ok(bytes);
+        }
+        let bytes = make(self.decoded()?)?;
+        fs::create_dir_all(&self.opts.cache_dname)
+            .map_err(|e| format!("{}: {}", self.opts.cache_dname.display(), e))?;
+        fs::write(&fname, &bytes).map_err(|e| format!("{}: {}", fname.display(), e))?;
+        Ok(bytes)
+    }
+
+    fn dimensions(&mut self) -> Result<(u32, u32), String> {
+        let s = self.get("size", |img| {
+            let (w, h) = img.dimensions();
+            Ok(format!("{} {}", w, h).into_bytes())
+        })?;
+        let s = String::from_utf8_lossy(&s);
+        let mut parts = s.split(' ').map(|p| p.parse::<u32>());
+        match (parts.next(), parts.next()) {
+            (Some(Ok(w)), Some(Ok(h))) => Ok((w, h)),
+            _ => Err(format!("Bad cached image size '{}'", s)),
+        }
+    }
+}
+
+/// Work out the size of the image *bytes* that lives at *rel* and make the smaller copies of it
+/// that *opts* asks for. JPEGs and PNGs are resized and re-encoded in the same format, while other
+/// images, like possibly animated GIFs, are left alone. Returns None if *bytes* isn't an image
+/// that can be read.
+pub fn process(rel: &str, bytes: &[u8], opts: &ImageOpts) -> Result<Option<Processed>, String> {
+    let format = match image::guess_format(bytes) {
+        Ok(format) => format,
+        Err(_) => return Ok(None),
+    };
+    let mut cache = Cache::new(opts, bytes);
+    let (width, height) = match cache.dimensions() {
+        Ok(dims) => dims,
+        Err(e) => {
+            warn!("{}: {}", rel, e);
+            return Ok(None);
+        }
+    };
+    let (out_format, ext) = match format {
+        ImageFormat::JPEG => (ImageOutputFormat::JPEG(opts.jpeg_quality), "jpg"),
+        ImageFormat::PNG => (ImageOutputFormat::PNG, "png"),
+        _ => {
+            return Ok(Some(Processed {
+                width,
+                height,
+                variants: vec![],
+            }))
+        }
+    };
+    let mut widths: Vec<u32> = opts
+        .widths
+        .iter()
+        .cloned()
+        .filter(|w| *w > 0 && *w < width)
+        .collect();
+    widths.sort();
+    widths.dedup();
+    let mut variants = vec![];
+    for w in widths {
+        let name = match out_format {
+            ImageOutputFormat::JPEG(q) => format!("{}w-q{}.{}", w, q, ext),
+            _ => format!("{}w.{}", w, ext),
+        };
+        let h = ((u64::from(height) * u64::from(w) + u64::from(width) / 2) / u64::from(width))
+            .max(1) as u32;
+        let out_format = out_format.clone();
+        let bytes = cache.get(&name, |img| {
+            debug!("Resizing {} to {}x{}", rel, w, h);
+            let mut out = vec![];
+            img.resize_exact(w, h, FilterType::Lanczos3)
+                .write_to(&mut out, out_format)
+                .map_err(|e| format!("{}: {}", rel, e))?;
+            Ok(out)
+        })?;
+        variants.push(Variant {
+            rel: variant_rel(rel, w),
+            width: w,
+            bytes,
+        });
+    }
+    Ok(Some(Processed {
+        width,
+        height,
+        variants,
+    }))
+}
+
+/// What's known about an image at some URL
+#[derive(Clone)]
+struct ImageInfo {
+    width: u32,
+    height: u32,
+    /// URLs of the image at different widths, including the original
+    srcset: Vec<(String, u32)>,
+}
+
+/// Processed images by the URL they're served at, so `<img>` tags that point at them can be
+/// filled in
+pub struct Images {
+    sizes: String,
+    by_url: HashMap<String, ImageInfo>,
+}
+impl Images {
+    pub fn new(opts: &ImageOpts) -> Self {
+        Images {
+            sizes: opts.sizes.clone(),
+            by_url: HashMap::new(),
+        }
+    }
+
+    /// Remember that the image *p* is at *url*, and that its variants are at the URLs *urls*
+    pub fn insert(&mut self, url: &str, p: &Processed, urls: Vec<String>) {
+        let mut srcset: Vec<(String, u32)> = urls
+            .into_iter()
+            .zip(p.variants.iter().map(|v| v.width))
+            .collect();
+        if !srcset.is_empty() {
+            srcset.push((url.to_string(), p.width));
+        }
+        self.by_url.insert(
+            url.to_string(),
+            ImageInfo {
+                width: p.width,
+                height: p.height,
+                srcset,
+            },
+        );
+    }
+
+    /// Let `<img>` tags that point at *alias* be filled in like ones that point at *url*, for images
+    /// that are linked to by another name than the one they're served under
+    pub fn alias(&mut self, alias: &str, url: &str) {
+        if let Some(info) = self.by_url.get(url).cloned() {
+            self.by_url.insert(alias.to_string(), info);
+        }
+    }
+
+    /// Add `width`, `height`, `srcset` and `sizes` to `<img>` tags in *html* that point at a known
+    /// image, leaving alone any of them that are already there
+    pub fn rewrite(&self, html: &str) -> String {
+        if self.by_url.is_empty() {
+            return html.to_string();
+        }
+        rewrite_tags(html, |tag| {
+            if tag.name != "img" || tag.closing {
+                return false;
+            }
+            let info = match tag.attr("src").and_then(|src| self.by_url.get(src)) {
+                Some(info) => info,
+                None => return false,
+            };
+            let mut changed = false;
+            if !tag.has_attr("width") && !tag.has_attr("height") {
+                tag.set_attr("width", &info.width.to_string());
+                tag.set_attr("height", &info.height.to_string());
+                changed = true;
+            }
+            if !info.srcset.is_empty() && !tag.has_attr("srcset") {
+                let srcset: Vec<String> = info
+                    .srcset
+                    .iter()
+                    .map(|(url, w)| format!("{} {}w", url, w))
+                    .collect();
+                tag.set_attr("srcset", &srcset.join(", "));
+                if !tag.has_attr("sizes") {
+                    tag.set_attr("sizes", &self.sizes);
+                }
+                changed = true;
+            }
+            changed
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{image_ext, process, ImageOpts, Images};
+    use image::{DynamicImage, GenericImageView, ImageOutputFormat};
+
+    fn opts(cache_dname: &std::path::Path) -> ImageOpts {
+        ImageOpts {
+            widths: vec![50, 10, 500],
+            sizes: "100vw".to_string(),
+            jpeg_quality: 80,
+            cache_dname: cache_dname.to_path_buf(),
+        }
+    }
+
+    fn png(w: u32, h: u32) -> Vec<u8> {
+        let mut out = vec![];
+        DynamicImage::new_rgb8(w, h)
+            .write_to(&mut out, ImageOutputFormat::PNG)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn variants() {
+        let dir = tempfile::tempdir().unwrap();
+        let bytes = png(100, 40);
+        assert_eq!(image_ext(&bytes), Some("png"));
+        let p = process("img/a.png", &bytes, &opts(dir.path()))
+            .unwrap()
+            .unwrap();
+        assert_eq!((p.width, p.height), (100, 40));
+        let rels: Vec<&str> = p.variants.iter().map(|v| v.rel.as_str()).collect();
+        assert_eq!(rels, vec!["img/a-10w.png", "img/a-50w.png"]);
+        let small = image::load_from_memory(&p.variants[1].bytes).unwrap();
+        assert_eq!((small.width(), small.height()), (50, 20));
+        // Everything comes from the cache the second time
+        let cached = process("img/a.png", &bytes, &opts(dir.path()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(cached.variants[1].bytes, p.variants[1].bytes);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 3);
+        assert!(process("a.txt", b"hello", &opts(dir.path()))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn rewrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let p = process("a.png", &png(100, 40), &opts(dir.path()))
+            .unwrap()
+            .unwrap();
+        let mut images = Images::new(&opts(dir.path()));
+        images.insert(
+            "/a.png",
+            &p,
+            vec!["/a-10w.png".to_string(), "/a-50w.png".to_string()],
+        );
+        assert_eq!(
+            images.rewrite("<img src='/a.png' alt='A'><img src='/b.png'>"),
+            "<img src=\"/a.png\" alt=\"A\" width=\"100\" height=\"40\" \
+             srcset=\"/a-10w.png 10w, /a-50w.png 50w, /a.png 100w\" sizes=\"100vw\">\
+             <img src='/b.png'>"
+        );
+        // A fingerprinted image is still found by the name posts link to it by
+        images.alias("/orig.png", "/a.png");
+        images.alias("/nothing.png", "/b.png");
+        assert_eq!(
+            images.rewrite("<img src='/orig.png'><img src='/nothing.png'>"),
+            "<img src=\"/orig.png\" width=\"100\" height=\"40\" \
+             srcset=\"/a-10w.png 10w, /a-50w.png 50w, /a.png 100w\" sizes=\"100vw\">\
+             <img src='/nothing.png'>"
+        );
+    }
+}
@@ -0,0 +1,427 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::read_to_string;
+use std::path::{Component, Path, PathBuf};
+
+use crate::check::{Diagnostic, Level};
+use crate::site::SiteUrl;
+use crate::util::fs::{paths_with_extension, recursive_find_files};
+use crate::util::html::{tokenize, Token};
+
+/// Attributes that hold a URL
+const URL_ATTRS: &[&str] = &["href", "src", "poster"];
+
+/// Schemes of links that aren't to pages at all, so there's nothing to check
+const SKIPPED_SCHEMES: &[&str] = &["mailto:", "tel:", "javascript:", "data:"];
+
+/// A link on a built page
+#[derive(Debug)]
+struct Link {
+    url: String,
+    /// On a page with several posts, the link to the page of the post it's in
+    post: Option<String>,
+}
+
+/// What's in a built page that matters for links
+#[derive(Debug, Default)]
+struct Page {
+    links: Vec<Link>,
+    /// Everything a `#fragment` can point to
+    ids: HashSet<String>,
+}
+
+fn scan(html: &str) -> Page {
+    let mut page = Page::default();
+    // Each post on a page has its title in a post_title element, which links to its own page if
+    // there are others on the page too
+    let mut articles = 0usize;
+    let mut post: Option<String> = None;
+    let mut title: Option<String> = None;
+    for token in tokenize(html) {
+        let tag = match token {
+            Token::Tag(tag, _) => tag,
+            _ => continue,
+        };
+        if tag.closing {
+            if tag.name == "article" {
+                articles = articles.saturating_sub(1);
+                if articles == 0 {
+                    post = None;
+                }
+            }
+            if title.as_ref() == Some(&tag.name) {
+                title = None;
+            }
+            continue;
+        }
+        if tag.name == "article" {
+            articles += 1;
+        }
+        let class = tag.attr("class").unwrap_or_default();
+        if articles > 0 && class.split_whitespace().any(|c| c == "post_title") {
+            title = Some(tag.name.clone());
+        } else if title.is_some() && tag.name == "a" {
+            post = tag.attr("href").map(|h| h.trim().to_string());
+        }
+        if let Some(id) = tag.attr("id") {
+            page.ids.insert(id.to_string());
+        }
+        if tag.name == "a" {
+            if let Some(name) = tag.attr("name") {
+                page.ids.insert(name.to_string());
+            }
+        }
+        let mut push = |url: &str| {
+            page.links.push(Link {
+                url: url.to_string(),
+                post: post.clone(),
+            })
+        };
+        for attr in URL_ATTRS {
+            if let Some(url) = tag.attr(attr) {
+                push(url.trim());
+            }
+        }
+        // Each candidate is a URL followed by a width or density
+        if let Some(srcset) = tag.attr("srcset") {
+            srcset
+                .split(',')
+                .filter_map(|c| c.split_whitespace().next())
+                .for_each(&mut push);
+        }
+    }
+    page
+}
+
+/// Undo `%XX` escapes in *s*, leaving any that aren't valid UTF-8 alone
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(Ok(b)) = s.get(i + 1..i + 3).map(|h| u8::from_str_radix(h, 16)) {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8(out).unwrap_or_else(|_| s.to_string())
+}
+
+/// Whether *url* goes to another site
+fn is_external(url: &str) -> bool {
+    if url.starts_with("//") {
+        return true;
+    }
+    // A scheme is letters, digits, `+`, `-` and `.` before the first `:`, which can't come after
+    // a `/`, `?` or `#`
+    match url.find([':', '/', '?', '#']) {
+        Some(i) if url[i..].starts_with(':') => {
+            i > 0
+                && url[..i]
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        }
+        _ => false,
+    }
+}
+
+/// The file in the build directory that *path* from a link on the page at *page* goes to, both
+/// relative to the build directory, or None if it goes above the build directory
+fn resolve(page: &Path, path: &str) -> Option<PathBuf> {
+    let mut parts: Vec<String> = if path.starts_with('/') {
+        vec![]
+    } else {
+        page.parent()
+            .unwrap_or_else(|| Path::new(""))
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(s) => Some(s.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect()
+    };
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            part => parts.push(percent_decode(part)),
+        }
+    }
+    Some(parts.iter().collect())
+}
+
+/// What `check_links` found
+#[derive(Debug, Default)]
+pub struct LinkReport {
+    /// How many pages were looked at
+    pub pages: usize,
+    pub broken: Vec<Diagnostic>,
+    /// Every link to another site, with the pages that have it
+    pub external: BTreeMap<String, BTreeSet<PathBuf>>,
+}
+
+/// The file in the build directory that the link to *path* on the page at *page* goes to, both
+/// relative to the build directory, or None if it goes above the build directory. A directory
+/// means its `index.html`.
+fn target(build_dname: &Path, page: &Path, path: &str) -> Option<PathBuf> {
+    let target = if path.is_empty() {
+        page.to_path_buf()
+    } else {
+        resolve(page, path)?
+    };
+    if build_dname.join(&target).is_dir() {
+        Some(target.join("index.html"))
+    } else {
+        Some(target)
+    }
+}
+
+/// The first line of the file at *path* that has *url* in it
+fn line_of(path: &Path, url: &str) -> Option<usize> {
+    let text = read_to_string(path).ok()?;
+    text.lines().position(|l| l.contains(url)).map(|i| i + 1)
+}
+
+/// Look at every link in each HTML page under *build_dname* and make sure the file it goes to was
+/// built, along with the element its `#fragment` names if it has one. Links from the root of the
+/// host and full URLs are the blog's own if they're under *site*. Problems in a post are reported
+/// against the post itself, found in *sources* by the path of its page relative to *build_dname*,
+/// whether they're on its own page or on one with other posts like the index. Links to other
+/// sites are only collected, never fetched. The build directory needs an `index.html`, or it
+/// hasn't been built.
+pub fn check_links(
+    build_dname: &Path,
+    site: &SiteUrl,
+    sources: &HashMap<PathBuf, PathBuf>,
+) -> LinkReport {
+    let mut report = LinkReport::default();
+    if !build_dname.join("index.html").is_file() {
+        report.broken.push(Diagnostic::new(
+            build_dname,
+            None,
+            Level::Error,
+            "not built yet, run `reb build` before checking links".to_string(),
+        ));
+        return report;
+    }
+    let fnames = recursive_find_files(&build_dname.to_string_lossy());
+    let mut pages = BTreeMap::new();
+    for fname in paths_with_extension(&fnames, ".html") {
+        let rel = fname.strip_prefix(build_dname).unwrap().to_path_buf();
+        match read_to_string(&fname) {
+            Ok(html) => {
+                pages.insert(rel, scan(&html));
+            }
+            Err(e) => {
+                report
+                    .broken
+                    .push(Diagnostic::new(&fname, None, Level::Error, e.to_string()))
+            }
+        }
+    }
+    report.pages = pages.len();
+    // The path of *url* on the page at *rel* from the root of the build, and its fragment
+    let split = |rel: &Path, url: &str| -> Option<(Option<PathBuf>, Option<String>)> {
+        let own = site.site_path(url);
+        if own.is_none() && is_external(url) {
+            return None;
+        }
+        let local = own.unwrap_or_else(|| url.to_string());
+        let (path, fragment) = match local.find('#') {
+            Some(i) => (&local[..i], Some(percent_decode(&local[i + 1..]))),
+            None => (&local[..], None),
+        };
+        let path = path.split('?').next().unwrap();
+        Some((target(build_dname, rel, path), fragment))
+    };
+    for (rel, page) in &pages {
+        let broken = |link: &Link, why: &str| {
+            let source = sources.get(rel).or_else(|| {
+                let post = link.post.as_ref()?;
+                let (post_page, _) = split(rel, post)?;
+                sources.get(&post_page?)
+            });
+            match source {
+                Some(source) => Diagnostic::new(
+                    source,
+                    line_of(source, &link.url),
+                    Level::Error,
+                    format!(
+                        "broken link to '{}' on {}: {}",
+                        link.url,
+                        build_dname.join(rel).display(),
+                        why
+                    ),
+                ),
+                None => Diagnostic::new(
+                    &build_dname.join(rel),
+                    None,
+                    Level::Error,
+                    format!("broken link to '{}': {}", link.url, why),
+                ),
+            }
+        };
+        for link in &page.links {
+            let url = &link.url;
+            if SKIPPED_SCHEMES.iter().any(|s| url.starts_with(s)) {
+                continue;
+            }
+            if site.site_path(url).is_none() && url.starts_with('/') && !url.starts_with("//") {
+                report.broken.push(broken(link, "goes outside the site"));
+                continue;
+            }
+            let (target, fragment) = match split(rel, url) {
+                Some((Some(target), fragment)) => (target, fragment),
+                Some((None, _)) => {
+                    report.broken.push(broken(link, "goes outside the site"));
+                    continue;
+                }
+                None => {
+                    report
+                        .external
+                        .entry(url.clone())
+                        .or_default()
+                        .insert(build_dname.join(rel));
+                    continue;
+                }
+            };
+            if !build_dname.join(&target).is_file() {
+                report.broken.push(broken(link, "no such file"));
+                continue;
+            }
+            // An empty fragment or `#top` goes to the top of any page
+            let fragment = match fragment {
+                Some(f) if !f.is_empty() && f != "top" => f,
+                _ => continue,
+            };
+            if let Some(target_page) = pages.get(&target) {
+                if !target_page.ids.contains(&fragment) {
+                    report
+                        .broken
+                        .push(broken(link, &format!("no element with id '{}'", fragment)));
+                }
+            }
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_links, is_external, resolve};
+    use crate::site::SiteUrl;
+    use std::collections::HashMap;
+    use std::fs::{create_dir_all, write};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn urls() {
+        assert!(is_external("https://example.com/"));
+        assert!(is_external("//example.com/a.png"));
+        assert!(!is_external("/posts/a.html"));
+        assert!(!is_external("a.html#x:y"));
+        assert_eq!(
+            resolve(Path::new("posts/a.html"), "../static/b%20c.png"),
+            Some(PathBuf::from("static/b c.png"))
+        );
+        assert_eq!(
+            resolve(Path::new("posts/a.html"), "/series/x/"),
+            Some(PathBuf::from("series/x"))
+        );
+        assert_eq!(resolve(Path::new("index.html"), "../up.html"), None);
+    }
+
+    #[test]
+    fn links() {
+        let dir = tempfile::tempdir().unwrap();
+        let build = dir.path();
+        create_dir_all(build.join("posts")).unwrap();
+        create_dir_all(build.join("static")).unwrap();
+        write(build.join("static/style.css"), "").unwrap();
+        write(
+            build.join("index.html"),
+            "<link href='/static/style.css' /><a href='/posts/a.html#intro'>A</a>\
+             <a href='https://example.com/'>Out</a><a href='mailto:me@example.com'>Me</a>\
+             <article><h1 class='post_title'><a href='posts/a.html'>A</a></h1>\
+             <p><a href='/posts/gone.html'>Gone</a></p></article><a href='/nowhere.html'>X</a>",
+        )
+        .unwrap();
+        write(
+            build.join("posts/a.html"),
+            "<h2 id='intro'>Intro</h2><a href='#outro'>Down</a><a href='b.html'>B</a>\
+             <img srcset='/static/a-400.jpg 400w, /static/style.css 800w' />\
+             <a href='../#top'>Home</a><a href='https://example.com/'>Out</a>\
+             <a href='https://example.org/posts/a.html#intro'>Self</a>",
+        )
+        .unwrap();
+        write(
+            build.join("posts/b.html"),
+            "<p><a href='missing.html'>Gone</a> <a href='/posts/gone.html'>Gone</a></p>",
+        )
+        .unwrap();
+        let source = dir.path().join("b.reb");
+        write(&source, "Title: A\n\n[Gone](/posts/gone.html)\n").unwrap();
+        let mut sources = HashMap::new();
+        sources.insert(PathBuf::from("posts/a.html"), PathBuf::from("posts/a.reb"));
+        sources.insert(PathBuf::from("posts/b.html"), source.clone());
+        let site = SiteUrl::new("https://example.org", false).unwrap();
+        let report = check_links(build, &site, &sources);
+        assert_eq!(report.pages, 3);
+        let broken: Vec<String> = report.broken.iter().map(|d| d.to_string()).collect();
+        let page = build.join("posts/a.html");
+        assert_eq!(
+            broken,
+            vec![
+                // On the index, problems in a post are the post's
+                format!(
+                    "posts/a.reb: error: broken link to '/posts/gone.html' on {}: no such file",
+                    build.join("index.html").display()
+                ),
+                format!(
+                    "{}: error: broken link to '/nowhere.html': no such file",
+                    build.join("index.html").display()
+                ),
+                format!(
+                    "posts/a.reb: error: broken link to '#outro' on {}: no element with id 'outro'",
+                    page.display()
+                ),
+                format!(
+                    "posts/a.reb: error: broken link to '/static/a-400.jpg' on {}: no such file",
+                    page.display()
+                ),
+                // With the line it's on, if it's in the post as it is on the page
+                format!(
+                    "{}: error: broken link to 'missing.html' on {}: no such file",
+                    source.display(),
+                    build.join("posts/b.html").display()
+                ),
+                format!(
+                    "{}:3: error: broken link to '/posts/gone.html' on {}: no such file",
+                    source.display(),
+                    build.join("posts/b.html").display()
+                ),
+            ]
+        );
+        assert_eq!(report.external.len(), 1);
+        assert_eq!(report.external["https://example.com/"].len(), 2);
+    }
+
+    #[test]
+    fn not_built() {
+        // An empty build directory, or one with only the directories reb makes, isn't a build
+        let dir = tempfile::tempdir().unwrap();
+        create_dir_all(dir.path().join("posts")).unwrap();
+        let report = check_links(dir.path(), &SiteUrl::default(), &HashMap::new());
+        assert_eq!(report.pages, 0);
+        assert_eq!(
+            report.broken[0].msg,
+            "not built yet, run `reb build` before checking links"
+        );
+    }
+}
@@ -1,6 +1,25 @@
+mod archetype;
+mod assets;
+mod authors;
+mod bundle;
+mod check;
+mod editor;
+mod feed;
+mod highlight;
+mod i18n;
+mod images;
+mod links;
+mod menu;
+mod minify;
 mod post;
+mod related;
+mod search;
+mod series;
+mod site;
 mod template;
+mod toc;
 mod util;
+mod words;
 
 //#[macro_use]
 extern crate structopt;
@@ -9,11 +28,13 @@ extern crate log;
 extern crate chrono;
 extern crate config;
 extern crate env_logger;
+extern crate image;
 extern crate rand;
 extern crate tempfile;
 
-use std::fs::{copy, create_dir_all, metadata, File, OpenOptions};
-use std::io::{BufReader, Seek, SeekFrom, Write};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{create_dir_all, metadata, read, read_to_string, OpenOptions};
+use std::io::{Cursor, Read, Write};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 
@@ -21,12 +42,41 @@ use chrono::Datelike;
 use config::Config;
 use config::File as ConfigFile;
 use structopt::StructOpt;
-use tempfile::NamedTempFile;
 
-use post::file::File as PostFile;
-use template::{begin_html, css, end_html, page_footer, page_header, post_footer, post_header};
-use util::fs::{paths_with_extension, recursive_find_files};
-use util::gen_id;
+use archetype::{fill, load_archetype, Placeholders};
+use assets::StaticDir;
+use authors::{Author, Authors};
+use bundle::{find_assets, rewrite_links};
+use check::{
+    check_authors, check_config_paths, check_post, check_post_set, check_series,
+    check_translations, load_post, Diagnostic, Level,
+};
+use editor::{edit_until_valid, resolve_editor};
+use feed::{atom, Entry};
+use highlight::{highlight_code_blocks, HighlightOpts};
+use i18n::{I18n, Strings};
+use images::{image_ext, process as process_image, ImageOpts, Images};
+use links::check_links;
+use menu::{menu_from_config, MenuItem};
+use minify::Minifier;
+use post::file::{File as PostFile, FileOpts, Kind};
+use post::value::parse_date;
+use related::{related, Doc};
+use search::{index_json, SearchDoc};
+use series::{find_series, Series};
+use site::SiteUrl;
+use template::{
+    author_index, begin_html, css, end_html, feed_link, highlight_css, hreflang_links, meta_tags,
+    page_footer, page_header, post_footer, post_header, post_layout, search_js, search_page,
+    series_box, series_index, standalone_page, toc, PageFooter, PageMeta, PostHeader, PostLink,
+};
+use toc::{add_heading_ids, prefix_ids};
+use util::fs::{
+    copy_if_changed, paths_with_extension, recursive_find_files, search_path, write_if_changed,
+};
+use util::id::{gen_unique_id, IdStrategy};
+use util::slug::SlugOpts;
+use words::{count_words, excerpt};
 
 #[derive(StructOpt, Debug)]
 #[structopt(name = "reb")]
@@ -44,6 +94,7 @@ enum CommandArgs {
     Init {
         #[structopt(short = "f", long = "force")]
         /// Force overwriting existing config
+        #[allow(dead_code)]
         force: bool,
     },
     #[structopt(name = "build")]
@@ -53,28 +104,61 @@ enum CommandArgs {
         /// Force a rebuild of all output files
         rebuild: bool,
     },
+    #[structopt(name = "check")]
+    /// Validate all posts and the config, exiting non-zero if there are problems
+    Check {
+        #[structopt(short = "W", long = "deny-warnings")]
+        /// Treat warnings as errors
+        deny_warnings: bool,
+        #[structopt(long = "links")]
+        /// Also check every link in the built site, which has to have been built already
+        links: bool,
+        #[structopt(long = "external")]
+        /// With --links, list links to other sites for checking by hand. They are never fetched.
+        external: bool,
+    },
     #[structopt(name = "create")]
     /// Compose a new blog post
     Create {
+        #[structopt(long = "no-edit")]
+        /// Save the post without opening it in an editor
+        no_edit: bool,
+        #[structopt(long = "body-file")]
+        /// Read the post body from this file, or from stdin if it is -
+        body_file: Option<String>,
+        #[structopt(long = "date")]
+        /// The date of the post, in RFC 2822, RFC 3339 or YYYY-MM-DD format, instead of now
+        date: Option<String>,
+        #[structopt(long = "tag", number_of_values = 1)]
+        /// Tag the post, and can be given more than once
+        tags: Vec<String>,
+        #[structopt(long = "author", number_of_values = 1)]
+        /// The key of an author in the [authors] config table, instead of strings.blog_author. Can
+        /// be given more than once for a post with several authors.
+        authors: Vec<String>,
+        #[structopt(long = "kind")]
+        /// Start from archetypes/<kind>.reb instead of archetypes/default.reb
+        kind: Option<String>,
         /// The title of the post
         #[structopt(required = true)]
         title: Vec<String>,
     },
 }
 
-fn find_all_post_files(post_dname: &str) -> Vec<PostFile> {
+/// Every file in *post_dname*. With *pages*, they're all read as pages.
+fn find_all_post_files(post_dname: &str, pages: bool) -> Vec<PostFile> {
     let post_files = recursive_find_files(post_dname);
     let post_files = paths_with_extension(&post_files, ".reb");
     let post_files: Vec<PostFile> = {
         let mut v = vec![];
         for fname in post_files {
-            let buf = BufReader::new(File::open(&fname).unwrap());
-            let mod_time = metadata(&fname).unwrap().modified().unwrap();
-            let post = PostFile::new_from_buf(Box::new(buf), Some(mod_time), None);
-            if post.is_err() {
-                error!("{}", post.unwrap_err());
-            } else {
-                v.push(post.unwrap());
+            let opts = FileOpts {
+                strict_headers: true,
+                page: pages,
+            };
+            match PostFile::new_from_file(&fname, Some(opts)) {
+                Ok(post) => v.push(post),
+                Err(e) => error!("{}: {}", fname.display(), e),
             }
         }
         v
@@ -82,45 +166,401 @@ fn find_all_post_files(post_dname: &str) -> Vec<PostFile> {
     post_files
 }
 
+/// The IDs of every post in *post_dname*. Headers aren't required to be well-formed so that IDs of
+/// half-finished posts are still found
+fn find_all_post_ids(post_dname: &str) -> HashSet<String> {
+    let post_files = recursive_find_files(post_dname);
+    let post_files = paths_with_extension(&post_files, ".reb");
+    let mut ids = HashSet::new();
+    for fname in post_files {
+        let opts = FileOpts {
+            strict_headers: false,
+            page: false,
+        };
+        match PostFile::new_from_file(&fname, Some(opts)) {
+            Ok(post) => {
+                if let Some(id) = post.get_header("id") {
+                    ids.insert(id);
+                }
+            }
+            Err(e) => warn!("{}: {}", fname.display(), e),
+        }
+    }
+    ids
+}
+
 fn init(args: Args, _conf: Config) -> Result<(), String> {
     trace!("Calling init with {:?}", args);
     Ok(())
 }
 
-fn render_index(parser: &str, title: &str, subtitle: &str, posts: &[PostFile]) -> Vec<u8> {
-    let mut v = vec![];
-    write!(v, "{}", begin_html(title)).unwrap();
-    write!(v, "{}", page_header(&title, &subtitle)).unwrap();
-    for pf in posts {
-        v.extend(render_post_preview(&parser, &pf, true));
+/// Everything rendering pages needs that is the same for every page
+struct RenderCtx<'a> {
+    parser: &'a str,
+    assets: &'a StaticDir,
+    blog_title: &'a str,
+    blog_subtitle: &'a str,
+    slug_opts: &'a SlugOpts,
+    highlight: &'a HighlightOpts,
+    /// Posts with at least this many headings get a table of contents even without `TOC: true`.
+    /// 0 means never.
+    toc_min_headings: usize,
+    reading: &'a ReadingOpts,
+    authors: &'a Authors,
+    i18n: &'a I18n,
+    /// Whether there are feeds to link to
+    feeds: bool,
+    site: &'a SiteUrl,
+    /// The links in the site's navigation
+    menu: &'a [MenuItem],
+    footer: &'a FooterOpts,
+}
+impl<'a> RenderCtx<'a> {
+    /// The language *pf* is in
+    fn lang(&self, pf: &PostFile) -> String {
+        pf.lang()
+            .unwrap_or_else(|| self.i18n.default_lang().to_string())
+    }
+
+    fn post_link(&self, pf: &PostFile) -> String {
+        "/posts/".to_string() + &pf.get_long_rendered_filename(self.slug_opts)
+    }
+
+    fn blog_title(&self, strings: &'a Strings) -> &'a str {
+        strings.blog_title.as_deref().unwrap_or(self.blog_title)
     }
-    write!(v, "{}", page_footer()).unwrap();
-    write!(v, "{}", end_html()).unwrap();
-    v
 }
 
-fn render_post_header(pf: &PostFile, with_link: bool) -> Vec<u8> {
+/// Links from a post's page to other posts
+struct PostNav {
+    /// The post before this one
+    prev: Option<PostLink>,
+    /// The post after this one
+    next: Option<PostLink>,
+    related: Vec<PostLink>,
+    series: Option<SeriesNav>,
+    /// The post in other languages, as pairs of language code and link
+    translations: Vec<(String, String)>,
+}
+
+/// Where a post is in the series it belongs to
+struct SeriesNav {
+    name: String,
+    link: String,
+    /// Index of the post in *parts*
+    current: usize,
+    parts: Vec<PostLink>,
+}
+
+fn author_link(author: &Author) -> String {
+    format!("/authors/{}/", author.key)
+}
+
+fn series_link(i18n: &I18n, series: &Series) -> String {
+    format!("{}/series/{}/", i18n.prefix(&series.lang), series.slug)
+}
+
+fn feed_href(i18n: &I18n, lang: &str) -> String {
+    format!("{}/feed.xml", i18n.prefix(lang))
+}
+
+/// The PostNav for each of *posts*, which are newest first. Previous, next and related posts are
+/// only ever in the same language.
+fn post_navs(
+    ctx: &RenderCtx,
+    posts: &[PostFile],
+    series: &[Series],
+    related_count: usize,
+) -> Vec<PostNav> {
+    let links: Vec<PostLink> = posts
+        .iter()
+        .map(|pf| (pf.title(), ctx.post_link(pf)))
+        .collect();
+    let langs: Vec<String> = posts.iter().map(|pf| ctx.lang(pf)).collect();
+    let mut navs: Vec<PostNav> = posts
+        .iter()
+        .map(|_| PostNav {
+            prev: None,
+            next: None,
+            related: vec![],
+            series: None,
+            translations: vec![],
+        })
+        .collect();
+    for lang in ctx.i18n.langs() {
+        let idxs: Vec<usize> = (0..posts.len()).filter(|i| langs[*i] == lang).collect();
+        let docs: Vec<Doc> = idxs
+            .iter()
+            .map(|i| Doc {
+                tags: posts[*i].tags(),
+                title: posts[*i].title(),
+                body: posts[*i].get_body(),
+            })
+            .collect();
+        for (pos, rel) in related(&docs, related_count).into_iter().enumerate() {
+            let nav = &mut navs[idxs[pos]];
+            nav.prev = idxs.get(pos + 1).map(|i| links[*i].clone());
+            nav.next = if pos > 0 {
+                Some(links[idxs[pos - 1]].clone())
+            } else {
+                None
+            };
+            nav.related = rel.into_iter().map(|j| links[idxs[j]].clone()).collect();
+        }
+    }
+    for s in series {
+        let parts: Vec<PostLink> = s.parts.iter().map(|i| links[*i].clone()).collect();
+        for (current, i) in s.parts.iter().enumerate() {
+            navs[*i].series = Some(SeriesNav {
+                name: s.name.clone(),
+                link: series_link(ctx.i18n, s),
+                current,
+                parts: parts.clone(),
+            });
+        }
+    }
+    // Translations are grouped by the ID of the original post
+    let originals: Vec<String> = posts
+        .iter()
+        .map(|pf| pf.translation_of().unwrap_or_else(|| pf.id()))
+        .collect();
+    for i in 0..posts.len() {
+        navs[i].translations = (0..posts.len())
+            .filter(|j| *j != i && originals[*j] == originals[i] && langs[*j] != langs[i])
+            .map(|j| (langs[j].clone(), links[j].1.clone()))
+            .collect();
+    }
+    navs
+}
+
+/// What goes at the bottom of every page
+struct FooterOpts {
+    /// Like `© 2019–2026 John Doe`, if it's shown
+    copyright: Option<String>,
+    license: String,
+    license_url: String,
+    feed_links: bool,
+    text: String,
+}
+
+/// How reading time is shown in post headers
+struct ReadingOpts {
+    enabled: bool,
+    words_per_minute: usize,
+    /// For Chinese and Japanese, which aren't split into words with spaces
+    cjk_chars_per_minute: usize,
+}
+
+/// The start of a page in the language of *strings*, with a link to its feed and any
+/// *head_links* after that
+fn render_begin_html(ctx: &RenderCtx, title: &str, strings: &Strings, head_links: &str) -> String {
+    let mut css_hrefs = vec![ctx.assets.url("style.css")];
+    if ctx.highlight.enabled {
+        css_hrefs.push(ctx.assets.url("highlight.css"));
+    }
+    let mut links = String::new();
+    if ctx.feeds {
+        links += &feed_link(ctx.blog_title(strings), &feed_href(ctx.i18n, &strings.lang));
+    }
+    links += head_links;
+    begin_html(
+        title,
+        strings,
+        &css_hrefs,
+        &ctx.assets.url("img/favicon.png"),
+        &links,
+    )
+}
+
+/// How long descriptions made from the start of a post are
+const DESCRIPTION_LEN: usize = 160;
+
+/// The image shown with *pf* in link previews, as a site path or a URL. That's the one in its
+/// `Image` header if it can be found, and the header image if not.
+fn post_image(ctx: &RenderCtx, pf: &PostFile) -> String {
+    let image = match pf.image() {
+        Some(image) => image,
+        None => return ctx.assets.url_for_stem("img/header"),
+    };
+    if image.contains("://") || image.starts_with('/') {
+        image
+    } else if ctx.assets.contains(&image) {
+        ctx.assets.url(&image)
+    } else if pf.bundle_dname().is_some_and(|d| d.join(&image).is_file()) {
+        format!("{}/{}", bundle_url(pf, ctx.slug_opts), image)
+    } else {
+        warn!("{}: can't find image '{}'", pf.title(), image);
+        ctx.assets.url_for_stem("img/header")
+    }
+}
+
+/// Description, OpenGraph and Twitter card tags for the page at site path *page*. *image* is a
+/// site path or URL, and *post* is the post if the page is one.
+fn render_meta(
+    ctx: &RenderCtx,
+    page: &str,
+    title: &str,
+    description: &str,
+    image: &str,
+    post: Option<&PostFile>,
+) -> String {
+    let strings = ctx.i18n.get(&match post {
+        Some(pf) => ctx.lang(pf),
+        None => ctx.i18n.default_lang().to_string(),
+    });
+    let image = if image.starts_with('/') {
+        ctx.site.full_url(image)
+    } else {
+        Some(image.to_string())
+    };
+    let published = post.map(|pf| pf.date());
+    let modified = post.and_then(|pf| pf.modified());
+    meta_tags(&PageMeta {
+        title,
+        description,
+        kind: if post.is_some() { "article" } else { "website" },
+        site_name: ctx.blog_title(strings),
+        url: ctx.site.full_url(page).as_deref(),
+        image: image.as_deref(),
+        published: published.as_ref(),
+        modified: modified.as_ref(),
+    })
+}
+
+/// The header of the page at site path *page*
+fn render_page_header(ctx: &RenderCtx, strings: &Strings, page: &str) -> String {
+    page_header(
+        ctx.blog_title(strings),
+        strings
+            .blog_subtitle
+            .as_deref()
+            .unwrap_or(ctx.blog_subtitle),
+        &ctx.assets.url_for_stem("img/header"),
+        ctx.menu,
+        page,
+    )
+}
+
+fn render_page_footer(ctx: &RenderCtx, strings: &Strings) -> String {
+    let feed_href = feed_href(ctx.i18n, &strings.lang);
+    page_footer(
+        &PageFooter {
+            copyright: ctx.footer.copyright.as_deref(),
+            license: &ctx.footer.license,
+            license_url: &ctx.footer.license_url,
+            feed_href: if ctx.feeds && ctx.footer.feed_links {
+                Some(&feed_href)
+            } else {
+                None
+            },
+            text: &ctx.footer.text,
+        },
+        strings,
+    )
+}
+
+/// The index for *lang*, with *posts* and their rendered bodies. *alternates* are the indexes
+/// in other languages, as pairs of language code and URL.
+fn render_index(
+    ctx: &RenderCtx,
+    lang: &str,
+    posts: &[(&PostFile, &str)],
+    alternates: &[(String, String)],
+) -> Vec<u8> {
+    let strings = ctx.i18n.get(lang);
     let mut v = vec![];
+    let subtitle = strings
+        .blog_subtitle
+        .as_deref()
+        .unwrap_or(ctx.blog_subtitle);
+    let meta = render_meta(
+        ctx,
+        &format!("{}/", ctx.i18n.prefix(lang)),
+        ctx.blog_title(strings),
+        subtitle,
+        &ctx.assets.url_for_stem("img/header"),
+        None,
+    );
     write!(
         v,
         "{}",
-        post_header(
-            pf.get_header("title").unwrap(),
-            pf.get_header("author").unwrap(),
-            if with_link {
-                Some("/posts/".to_string() + &pf.get_long_rendered_filename())
-            } else {
-                None
-            },
+        render_begin_html(
+            ctx,
+            ctx.blog_title(strings),
+            strings,
+            &(hreflang_links(alternates) + &meta)
         )
     )
     .unwrap();
+    write!(
+        v,
+        "{}",
+        render_page_header(ctx, strings, &format!("{}/", ctx.i18n.prefix(lang)))
+    )
+    .unwrap();
+    for (pf, body) in posts {
+        v.extend(render_post_preview(ctx, pf, body, ctx.post_link(pf)));
+    }
+    write!(v, "{}", render_page_footer(ctx, strings)).unwrap();
+    write!(v, "{}", end_html()).unwrap();
     v
 }
 
-fn render_post_body(parser: &str, pf: &PostFile) -> Vec<u8> {
+fn render_post_header(
+    ctx: &RenderCtx,
+    pf: &PostFile,
+    link: Option<String>,
+    body_html: &str,
+    nav: Option<&PostNav>,
+) -> Vec<u8> {
+    let strings = ctx.i18n.get(&ctx.lang(pf));
+    let reading = if ctx.reading.enabled {
+        let count = count_words(body_html);
+        Some((
+            count.total(),
+            count.minutes(
+                ctx.reading.words_per_minute,
+                ctx.reading.cjk_chars_per_minute,
+            ),
+        ))
+    } else {
+        None
+    };
+    let authors: Vec<(String, Option<String>)> = pf
+        .authors()
+        .into_iter()
+        .map(|name| match ctx.authors.find(&name) {
+            Some(a) => (a.name.clone(), Some(author_link(a))),
+            None => (name, None),
+        })
+        .collect();
+    let translations: Vec<(String, String, String)> = nav
+        .map(|nav| nav.translations.as_slice())
+        .unwrap_or_default()
+        .iter()
+        .map(|(lang, link)| (ctx.i18n.get(lang).name.clone(), lang.clone(), link.clone()))
+        .collect();
+    let date = pf.date();
+    let modified = pf.modified();
+    let tags = pf.tags();
+    let header = PostHeader {
+        title: pf.title(),
+        link,
+        authors: &authors,
+        date: &date,
+        modified: modified.as_ref(),
+        tags: &tags,
+        reading,
+        translations: &translations,
+    };
     let mut v = vec![];
-    let mut proc = Command::new(parser)
+    write!(v, "{}", post_header(&header, strings)).unwrap();
+    v
+}
+
+/// The post's body as HTML, ready to go in a page
+fn render_body_html(ctx: &RenderCtx, pf: &PostFile) -> String {
+    let mut proc = Command::new(ctx.parser)
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .spawn()
@@ -137,39 +577,273 @@ fn render_post_body(parser: &str, pf: &PostFile) -> Vec<u8> {
     let output = proc
         .wait_with_output()
         .expect("Failed to get post output from parser stdout");
+    let mut html = String::from_utf8_lossy(&output.stdout).into_owned();
+    // Relative links to a bundle's files have to work from the index too
+    if let Some(dname) = pf.bundle_dname() {
+        html = rewrite_links(&html, &find_assets(dname), &bundle_url(pf, ctx.slug_opts));
+    }
+    highlight_code_blocks(&html, ctx.highlight)
+}
+
+fn render_post_body(html: &str) -> Vec<u8> {
+    let mut v = vec![];
     writeln!(v, "<div class='post_body'>").unwrap();
-    v.extend(output.stdout);
+    v.extend(html.as_bytes());
     writeln!(v, "</div> <!-- post_body -->").unwrap();
     v
 }
 
-fn render_post_footer() -> Vec<u8> {
+fn render_post_footer(nav: Option<&PostNav>, strings: &Strings) -> Vec<u8> {
     let mut v = vec![];
-    write!(v, "{}", post_footer()).unwrap();
+    let footer = match nav {
+        Some(nav) => post_footer(nav.prev.as_ref(), nav.next.as_ref(), &nav.related, strings),
+        None => post_footer(None, None, &[], strings),
+    };
+    write!(v, "{}", footer).unwrap();
     v
 }
 
-fn render_post(parser: &str, blog_title: &str, blog_subtitle: &str, pf: &PostFile) -> Vec<u8> {
+fn render_post(ctx: &RenderCtx, pf: &PostFile, body_html: &str, nav: &PostNav) -> Vec<u8> {
+    let lang = ctx.lang(pf);
+    let strings = ctx.i18n.get(&lang);
     let mut v = vec![];
-    let title = pf.get_header("title").unwrap() + " | " + blog_title;
-    write!(v, "{}", begin_html(&title)).unwrap();
-    write!(v, "{}", page_header(&blog_title, &blog_subtitle)).unwrap();
-    v.extend(&render_post_preview(&parser, &pf, false));
-    write!(v, "{}", page_footer()).unwrap();
+    let title = pf.title() + " | " + ctx.blog_title(strings);
+    // Translations point at each other, and at themselves
+    let mut alternates = nav.translations.clone();
+    if !alternates.is_empty() {
+        alternates.insert(0, (lang.clone(), ctx.post_link(pf)));
+    }
+    let description = pf
+        .description()
+        .unwrap_or_else(|| excerpt(body_html, DESCRIPTION_LEN));
+    let meta = render_meta(
+        ctx,
+        &ctx.post_link(pf),
+        &pf.title(),
+        &description,
+        &post_image(ctx, pf),
+        Some(pf),
+    );
+    write!(
+        v,
+        "{}",
+        render_begin_html(ctx, &title, strings, &(hreflang_links(&alternates) + &meta))
+    )
+    .unwrap();
+    write!(
+        v,
+        "{}",
+        render_page_header(ctx, strings, &ctx.post_link(pf))
+    )
+    .unwrap();
+    let (html, headings) = add_heading_ids(body_html);
+    let want_toc = match pf.toc() {
+        Some(b) => b,
+        None => ctx.toc_min_headings > 0 && headings.len() >= ctx.toc_min_headings,
+    };
+    let toc_html = if want_toc && !headings.is_empty() {
+        toc(&headings)
+    } else {
+        String::new()
+    };
+    let article =
+        String::from_utf8_lossy(&render_article(ctx, pf, None, &html, Some(nav))).into_owned();
+    write!(v, "{}", post_layout(&article, &toc_html)).unwrap();
+    write!(v, "{}", render_page_footer(ctx, strings)).unwrap();
     write!(v, "{}", end_html()).unwrap();
     v
 }
 
-fn render_post_preview(parser: &str, pf: &PostFile, with_links: bool) -> Vec<u8> {
+/// A post on a page with other posts, where its heading ids and any other ids in it start with
+/// its ID so they don't clash
+fn render_post_preview(ctx: &RenderCtx, pf: &PostFile, body_html: &str, link: String) -> Vec<u8> {
+    let (html, _) = add_heading_ids(body_html);
+    let html = prefix_ids(&html, &format!("{}-", pf.id()));
+    render_article(ctx, pf, Some(link), &html, None)
+}
+
+fn render_article(
+    ctx: &RenderCtx,
+    pf: &PostFile,
+    link: Option<String>,
+    body_html: &str,
+    nav: Option<&PostNav>,
+) -> Vec<u8> {
+    let strings = ctx.i18n.get(&ctx.lang(pf));
     let mut v = vec![];
-    writeln!(v, "<article>").unwrap();
-    v.extend(&render_post_header(&pf, with_links));
-    v.extend(&render_post_body(parser, &pf));
-    v.extend(&render_post_footer());
+    // Posts in another language than the page around them, like on an author's page, say so
+    writeln!(v, "<article lang='{}' dir='{}'>", strings.lang, strings.dir).unwrap();
+    v.extend(&render_post_header(ctx, pf, link, body_html, nav));
+    if let Some(series) = nav.and_then(|nav| nav.series.as_ref()) {
+        write!(
+            v,
+            "{}",
+            series_box(
+                &series.name,
+                &series.link,
+                series.current,
+                &series.parts,
+                strings
+            )
+        )
+        .unwrap();
+    }
+    v.extend(&render_post_body(body_html));
+    v.extend(&render_post_footer(nav, strings));
     writeln!(v, "</article>").unwrap();
     v
 }
 
+fn render_series_index(ctx: &RenderCtx, series: &SeriesNav, lang: &str) -> Vec<u8> {
+    let strings = ctx.i18n.get(lang);
+    let mut v = vec![];
+    let title = series.name.clone() + " | " + ctx.blog_title(strings);
+    let meta = render_meta(
+        ctx,
+        &series.link,
+        &series.name,
+        &i18n::fill(&strings.series_count, &[("n", series.parts.len())]),
+        &ctx.assets.url_for_stem("img/header"),
+        None,
+    );
+    write!(v, "{}", render_begin_html(ctx, &title, strings, &meta)).unwrap();
+    write!(v, "{}", render_page_header(ctx, strings, &series.link)).unwrap();
+    write!(v, "{}", series_index(&series.name, &series.parts, strings)).unwrap();
+    write!(v, "{}", render_page_footer(ctx, strings)).unwrap();
+    write!(v, "{}", end_html()).unwrap();
+    v
+}
+
+fn render_author_index(ctx: &RenderCtx, author: &Author, posts: &[PostLink]) -> Vec<u8> {
+    let strings = ctx.i18n.get(ctx.i18n.default_lang());
+    let mut v = vec![];
+    let title = author.name.clone() + " | " + ctx.blog_title(strings);
+    // Avatars can be in the static directory, so they get its URL and any fingerprint
+    let avatar_src = author.avatar.as_ref().map(|a| {
+        if ctx.assets.contains(a) {
+            ctx.assets.url(a)
+        } else {
+            a.clone()
+        }
+    });
+    let meta = render_meta(
+        ctx,
+        &author_link(author),
+        &author.name,
+        &author.bio,
+        &avatar_src
+            .clone()
+            .unwrap_or_else(|| ctx.assets.url_for_stem("img/header")),
+        None,
+    );
+    write!(v, "{}", render_begin_html(ctx, &title, strings, &meta)).unwrap();
+    write!(
+        v,
+        "{}",
+        render_page_header(ctx, strings, &author_link(author))
+    )
+    .unwrap();
+    write!(
+        v,
+        "{}",
+        author_index(author, avatar_src.as_deref(), posts, strings)
+    )
+    .unwrap();
+    write!(v, "{}", render_page_footer(ctx, strings)).unwrap();
+    write!(v, "{}", end_html()).unwrap();
+    v
+}
+
+/// A page that isn't a post, on its own
+fn render_page(ctx: &RenderCtx, pf: &PostFile, body_html: &str) -> Vec<u8> {
+    let strings = ctx.i18n.get(&ctx.lang(pf));
+    let mut v = vec![];
+    let title = pf.title() + " | " + ctx.blog_title(strings);
+    let description = pf
+        .description()
+        .unwrap_or_else(|| excerpt(body_html, DESCRIPTION_LEN));
+    let meta = render_meta(
+        ctx,
+        &pf.page_path(ctx.slug_opts),
+        &pf.title(),
+        &description,
+        &post_image(ctx, pf),
+        None,
+    );
+    write!(v, "{}", render_begin_html(ctx, &title, strings, &meta)).unwrap();
+    write!(
+        v,
+        "{}",
+        render_page_header(ctx, strings, &pf.page_path(ctx.slug_opts))
+    )
+    .unwrap();
+    write!(v, "{}", standalone_page(&pf.title(), body_html, strings)).unwrap();
+    write!(v, "{}", render_page_footer(ctx, strings)).unwrap();
+    write!(v, "{}", end_html()).unwrap();
+    v
+}
+
+/// Where the search page goes, as a site path
+const SEARCH_PAGE: &str = "/search/index.html";
+
+fn render_search(ctx: &RenderCtx) -> Vec<u8> {
+    let strings = ctx.i18n.get(ctx.i18n.default_lang());
+    let mut v = vec![];
+    let title = strings.search.clone() + " | " + ctx.blog_title(strings);
+    let meta = render_meta(
+        ctx,
+        "/search/",
+        &strings.search,
+        "",
+        &ctx.assets.url_for_stem("img/header"),
+        None,
+    );
+    write!(v, "{}", render_begin_html(ctx, &title, strings, &meta)).unwrap();
+    write!(v, "{}", render_page_header(ctx, strings, SEARCH_PAGE)).unwrap();
+    write!(
+        v,
+        "{}",
+        search_page(strings, "/search/index.json", &ctx.assets.url("search.js"))
+    )
+    .unwrap();
+    write!(v, "{}", render_page_footer(ctx, strings)).unwrap();
+    write!(v, "{}", end_html()).unwrap();
+    v
+}
+
+/// The Atom feed of the newest of *posts*, which are all in *lang*
+fn render_feed(ctx: &RenderCtx, lang: &str, posts: &[(&PostFile, &str)]) -> Vec<u8> {
+    let strings = ctx.i18n.get(lang);
+    let entries: Vec<Entry> = posts
+        .iter()
+        .map(|(pf, body)| Entry {
+            id: format!("urn:reb:post:{}", pf.id()),
+            title: pf.title(),
+            link: ctx.site.absolute(&ctx.post_link(pf)),
+            authors: pf
+                .authors()
+                .into_iter()
+                .map(|name| match ctx.authors.find(&name) {
+                    Some(a) => a.name.clone(),
+                    None => name,
+                })
+                .collect(),
+            published: pf.date(),
+            updated: pf.modified().unwrap_or_else(|| pf.date()),
+            content: ctx.site.rewrite_absolute(&ctx.assets.rewrite(body)),
+        })
+        .collect();
+    atom(
+        ctx.blog_title(strings),
+        &format!("urn:reb:feed:{}", lang),
+        &ctx.site.absolute(&feed_href(ctx.i18n, lang)),
+        &ctx.site.absolute(&format!("{}/", ctx.i18n.prefix(lang))),
+        lang,
+        &entries,
+    )
+    .into_bytes()
+}
+
 fn render_css() -> Vec<u8> {
     let mut v = vec![];
     write!(v, "{}", css()).unwrap();
@@ -178,59 +852,191 @@ fn render_css() -> Vec<u8> {
 
 fn build(args: Args, conf: Config) -> Result<(), String> {
     trace!("Calling build with {:?}", args);
-    let post_files = find_all_post_files(&conf.get_str("paths.post_dname").unwrap());
-    debug!("Found {} valid post files", post_files.len());
-    if post_files.is_empty() {
+    let rebuild = match args.cmd {
+        CommandArgs::Build { rebuild } => rebuild,
+        _ => unreachable!(),
+    };
+    // Pages can be in the posts directory too, if they say so
+    let (mut pages, mut post_files): (Vec<PostFile>, Vec<PostFile>) =
+        find_all_post_files(&conf.get_str("paths.post_dname").unwrap(), false)
+            .into_iter()
+            .partition(|pf| pf.kind() == Kind::Page);
+    pages.extend(find_all_post_files(
+        &conf.get_str("paths.pages_dname").unwrap(),
+        true,
+    ));
+    debug!(
+        "Found {} valid post files and {} pages",
+        post_files.len(),
+        pages.len()
+    );
+    if post_files.is_empty() && pages.is_empty() {
         return Ok(());
     }
+    // Newest first, with the ID deciding between posts from the same moment so the order never
+    // changes between builds
+    post_files.sort_by(|a, b| b.date().cmp(&a.date()).then_with(|| a.id().cmp(&b.id())));
     let build_dname = conf.get_str("paths.build_dname").unwrap();
     let parser = conf.get_str("paths.parse_bin").unwrap();
     let blog_title = conf.get_str("strings.blog_title").unwrap();
     let blog_subtitle = conf.get_str("strings.blog_subtitle").unwrap();
-    {
-        let fname = build_dname.clone() + "/index.html";
-        let mut fd = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(fname)
-            .unwrap();
-        match fd.write_all(&render_index(
-            &parser,
-            &blog_title,
-            &blog_subtitle,
-            &post_files,
-        )) {
-            Ok(_) => {}
-            Err(e) => {
-                return Err(e.to_string());
+    let slug_opts = get_slug_opts(&conf);
+    let mut minifier = Minifier::new(conf.get_bool("minify.enabled").unwrap());
+    // Static files come first so pages know where they ended up
+    let mut assets = StaticDir::new(
+        &(build_dname.clone() + "/static"),
+        conf.get_bool("assets.fingerprint").unwrap(),
+        rebuild,
+    );
+    // Files in the static directory win over the built-in ones
+    assets.add_dir(&conf.get_str("paths.static_dname").unwrap())?;
+    if !assets.contains("style.css") {
+        let css = minifier.css(&String::from_utf8_lossy(&render_css()));
+        assets.add_bytes("style.css", css.as_bytes(), None)?;
+    }
+    let highlight_opts = get_highlight_opts(&conf);
+    if highlight_opts.enabled && !assets.contains("highlight.css") {
+        let css = minifier.css(&highlight_css(&highlight_opts.theme)?);
+        assets.add_bytes("highlight.css", css.as_bytes(), None)?;
+    }
+    let search = conf.get_bool("search.enabled").unwrap();
+    if search && !assets.contains("search.js") {
+        assets.add_bytes("search.js", search_js().as_bytes(), None)?;
+    }
+    // The header image keeps whatever format it's in, so its extension depends on the content
+    let header_fname = conf.get_str("paths.blog_img_fname").unwrap();
+    let header_bytes = read(&header_fname).map_err(|e| format!("{}: {}", header_fname, e))?;
+    let header_rel = format!("img/header.{}", image_ext(&header_bytes).unwrap_or("jpg"));
+    if !assets.contains(&header_rel) {
+        assets.add_file(&header_rel, Path::new(&header_fname))?;
+    }
+    if !assets.contains("img/favicon.png") {
+        let favicon_fname = conf.get_str("paths.favicon_fname").unwrap();
+        assets.add_file("img/favicon.png", Path::new(&favicon_fname))?;
+    }
+    let image_opts = get_image_opts(&conf);
+    let mut images = Images::new(&image_opts);
+    for (rel, src) in assets.files() {
+        let bytes = read(&src).map_err(|e| format!("{}: {}", src.display(), e))?;
+        if let Some(p) = process_image(&rel, &bytes, &image_opts)? {
+            let mut urls = vec![];
+            for v in &p.variants {
+                assets.add_bytes(&v.rel, &v.bytes, None)?;
+                urls.push(assets.url(&v.rel));
             }
+            images.insert(&assets.url(&rel), &p, urls);
+            // Posts link to static images by their original names, which only become the
+            // fingerprinted ones after the images are filled in
+            images.alias(&format!("/static/{}", rel), &assets.url(&rel));
         }
     }
-    for post_file in &post_files {
-        let dname = build_dname.clone() + "/posts";
-        let fname = dname + "/" + &post_file.get_long_rendered_filename();
-        debug!("Rendering {} ...", fname);
-        let mut fd = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(fname)
-            .unwrap();
-        match fd.write_all(&render_post(
-            &parser,
-            &blog_title,
-            &blog_subtitle,
-            &post_file,
-        )) {
-            Ok(_) => {}
-            Err(e) => {
-                return Err(e.to_string());
+    let (num_copied, num_skipped, num_removed) = assets.finish()?;
+    info!(
+        "Static files: {} written, {} unchanged, {} stale removed",
+        num_copied, num_skipped, num_removed
+    );
+    let reading_opts = get_reading_opts(&conf);
+    let authors = Authors::from_config(&conf)?;
+    let mut langs: Vec<String> = post_files.iter().filter_map(|pf| pf.lang()).collect();
+    langs.sort();
+    langs.dedup();
+    let i18n = I18n::from_config(&conf, &langs)?;
+    let site = SiteUrl::from_config(&conf)?;
+    let menu = menu_from_config(&conf, &pages.iter().collect::<Vec<_>>(), &slug_opts)?;
+    let footer = get_footer_opts(&conf, &post_files);
+    let ctx = RenderCtx {
+        parser: &parser,
+        assets: &assets,
+        blog_title: &blog_title,
+        blog_subtitle: &blog_subtitle,
+        slug_opts: &slug_opts,
+        highlight: &highlight_opts,
+        toc_min_headings: conf.get_int("toc.min_headings").unwrap() as usize,
+        reading: &reading_opts,
+        authors: &authors,
+        i18n: &i18n,
+        feeds: conf.get_bool("feed.enabled").unwrap(),
+        site: &site,
+        menu: &menu,
+        footer: &footer,
+    };
+    // Bundle files have to be in place before any page that shows the post is rendered
+    for post_file in post_files.iter().chain(&pages) {
+        let bundle_dname = match post_file.bundle_dname() {
+            Some(d) => d,
+            None => continue,
+        };
+        let base_url = bundle_url(post_file, &slug_opts);
+        let out_dname = Path::new(&build_dname).join(base_url.trim_start_matches('/'));
+        for (rel, src) in find_assets(bundle_dname) {
+            if copy_if_changed(&src, &out_dname.join(&rel), rebuild)? {
+                debug!("Copied {} to {}", src.display(), out_dname.display());
             }
+            let bytes = read(&src).map_err(|e| format!("{}: {}", src.display(), e))?;
+            if let Some(p) = process_image(&rel, &bytes, &image_opts)? {
+                let mut urls = vec![];
+                for v in &p.variants {
+                    write_if_changed(&out_dname.join(&v.rel), &v.bytes, rebuild)?;
+                    urls.push(format!("{}/{}", base_url, v.rel));
+                }
+                images.insert(&format!("{}/{}", base_url, rel), &p, urls);
+            }
+        }
+    }
+    // Pages are rendered with links from the root of the blog, and to static files by their
+    // original names, which become real ones here
+    let mut finish_page = |html: &[u8], page: &str| {
+        let html = images.rewrite(&String::from_utf8_lossy(html));
+        let html = assets.rewrite(&html);
+        minifier.html(&site.rewrite(&html, page))
+    };
+    // Each post is parsed once, since it's shown on its own page, an index and a feed
+    let bodies: Vec<String> = post_files
+        .iter()
+        .map(|pf| render_body_html(&ctx, pf))
+        .collect();
+    let max_feed_entries = conf.get_int("feed.max_entries").unwrap() as usize;
+    let index_hrefs: Vec<(String, String)> = i18n
+        .langs()
+        .into_iter()
+        .map(|lang| (lang.to_string(), format!("{}/", i18n.prefix(lang))))
+        .collect();
+    for lang in i18n.langs() {
+        let posts: Vec<(&PostFile, &str)> = post_files
+            .iter()
+            .zip(&bodies)
+            .filter(|(pf, _)| ctx.lang(pf) == lang)
+            .map(|(pf, body)| (pf, body.as_str()))
+            .collect();
+        let dname = Path::new(&build_dname).join(i18n.prefix(lang).trim_start_matches('/'));
+        // Only the other languages' indexes are alternates, and only if there are any
+        let alternates: Vec<(String, String)> = if index_hrefs.len() > 1 {
+            index_hrefs.clone()
+        } else {
+            vec![]
         };
+        let html = render_index(&ctx, lang, &posts, &alternates);
+        let html = finish_page(&html, &format!("{}/index.html", i18n.prefix(lang)));
+        write_if_changed(&dname.join("index.html"), html.as_bytes(), rebuild)?;
+        if ctx.feeds {
+            let newest = &posts[..posts.len().min(max_feed_entries)];
+            write_if_changed(
+                &dname.join("feed.xml"),
+                &render_feed(&ctx, lang, newest),
+                rebuild,
+            )?;
+        }
     }
-    {
-        let fname = build_dname.clone() + "/static/style.css";
+    let series = find_series(&post_files, i18n.default_lang());
+    let navs = post_navs(
+        &ctx,
+        &post_files,
+        &series,
+        conf.get_int("related.count").unwrap() as usize,
+    );
+    for ((post_file, body), nav) in post_files.iter().zip(&bodies).zip(&navs) {
+        let dname = build_dname.clone() + "/posts";
+        let fname = dname + "/" + &post_file.get_long_rendered_filename(&slug_opts);
         debug!("Rendering {} ...", fname);
         let mut fd = OpenOptions::new()
             .create(true)
@@ -238,132 +1044,481 @@ fn build(args: Args, conf: Config) -> Result<(), String> {
             .truncate(true)
             .open(fname)
             .unwrap();
-        match fd.write_all(&render_css()) {
+        let html = render_post(&ctx, post_file, body, nav);
+        let html = finish_page(&html, &ctx.post_link(post_file));
+        match fd.write_all(html.as_bytes()) {
             Ok(_) => {}
             Err(e) => {
                 return Err(e.to_string());
             }
         };
     }
-    copy(
-        &conf.get_str("paths.blog_img_fname").unwrap(),
-        build_dname.clone() + "/static/img/header.jpg",
-    )
-    .unwrap();
-    copy(
-        &conf.get_str("paths.favicon_fname").unwrap(),
-        build_dname.clone() + "/static/img/favicon.png",
-    )
-    .unwrap();
+    for s in &series {
+        let fname = Path::new(&build_dname)
+            .join(i18n.prefix(&s.lang).trim_start_matches('/'))
+            .join("series")
+            .join(&s.slug)
+            .join("index.html");
+        let nav = navs[s.parts[0]].series.as_ref().unwrap();
+        let html = render_series_index(&ctx, nav, &s.lang);
+        let html = finish_page(&html, &(nav.link.clone() + "index.html"));
+        write_if_changed(&fname, html.as_bytes(), rebuild)?;
+    }
+    for page in &pages {
+        let fname = output_fname(page, &slug_opts);
+        debug!("Rendering page {} ...", fname.display());
+        let html = render_page(&ctx, page, &render_body_html(&ctx, page));
+        let html = finish_page(&html, &("/".to_string() + &fname.to_string_lossy()));
+        write_if_changed(
+            &Path::new(&build_dname).join(fname),
+            html.as_bytes(),
+            rebuild,
+        )?;
+    }
+    for author in authors.iter() {
+        let posts: Vec<PostLink> = post_files
+            .iter()
+            .filter(|pf| {
+                pf.authors()
+                    .iter()
+                    .any(|name| authors.find(name) == Some(author))
+            })
+            .map(|pf| (pf.title(), ctx.post_link(pf)))
+            .collect();
+        let fname = Path::new(&build_dname)
+            .join("authors")
+            .join(&author.key)
+            .join("index.html");
+        let html = render_author_index(&ctx, author, &posts);
+        let html = finish_page(&html, &(author_link(author) + "index.html"));
+        write_if_changed(&fname, html.as_bytes(), rebuild)?;
+    }
+    if search {
+        let docs: Vec<SearchDoc> = post_files
+            .iter()
+            .zip(&bodies)
+            .map(|(pf, body)| SearchDoc {
+                title: pf.title(),
+                url: site.link(SEARCH_PAGE, &ctx.post_link(pf)),
+                date: ctx.i18n.get(&ctx.lang(pf)).format_date(&pf.date()),
+                tags: pf.tags(),
+                html: body.clone(),
+            })
+            .collect();
+        let dname = Path::new(&build_dname).join("search");
+        write_if_changed(
+            &dname.join("index.json"),
+            index_json(&docs).as_bytes(),
+            rebuild,
+        )?;
+        let html = finish_page(&render_search(&ctx), SEARCH_PAGE);
+        write_if_changed(&dname.join("index.html"), html.as_bytes(), rebuild)?;
+    }
+    if let Some(summary) = minifier.summary() {
+        info!("{}", summary);
+    }
+    if conf.get_bool("links.check_after_build").unwrap() {
+        let report = check_links(
+            Path::new(&build_dname),
+            &site,
+            &post_sources(post_files.iter().chain(&pages), &slug_opts),
+        );
+        for d in &report.broken {
+            error!("{}", d);
+        }
+        if !report.broken.is_empty() {
+            return Err(format!("{} broken link(s) found", report.broken.len()));
+        }
+        info!("Checked the links on {} page(s)", report.pages);
+    }
     Ok(())
 }
 
 fn create(args: Args, conf: Config) -> Result<(), String> {
     trace!("Calling create with {:?}", args);
-    let cmd_args = args.cmd;
-    let title = &match cmd_args {
-        CommandArgs::Create { title } => title.join(" "),
-        _ => {
-            assert!(false);
-            String::new()
+    let (no_edit, body_file, date, tags, authors, kind, title) = match args.cmd {
+        CommandArgs::Create {
+            no_edit,
+            body_file,
+            date,
+            tags,
+            authors,
+            kind,
+            title,
+        } => (
+            no_edit,
+            body_file,
+            date,
+            tags,
+            authors,
+            kind,
+            title.join(" "),
+        ),
+        _ => unreachable!(),
+    };
+    let post_dname = conf.get_str("paths.post_dname").unwrap();
+    let id_strategy = conf
+        .get_str("ids.strategy")
+        .unwrap()
+        .parse::<IdStrategy>()?;
+    let existing_ids = find_all_post_ids(&post_dname);
+    let now = chrono::Local::now();
+    let date = match date {
+        Some(ref d) => parse_date(d).ok_or(format!("Could not parse date '{}'", d))?,
+        None => parse_date(&now.to_rfc2822()).unwrap(),
+    };
+    // IDs made from the date use the post's, so they agree with its Date header
+    let post_id = gen_unique_id(&id_strategy, &existing_ids, &date);
+    let body = match body_file.as_deref() {
+        Some("-") => {
+            let mut s = String::new();
+            std::io::stdin()
+                .read_to_string(&mut s)
+                .map_err(|e| e.to_string())?;
+            Some(s)
         }
+        Some(fname) => Some(read_to_string(fname).map_err(|e| format!("{}: {}", fname, e))?),
+        None => None,
     };
-    let author = &conf.get_str("strings.blog_author").unwrap();
-    let post_id = gen_id();
-    let date = chrono::Local::now().to_rfc2822();
-    let editor = conf.get_str("paths.editor_bin").unwrap();
-    let mut file = NamedTempFile::new().unwrap();
-    debug!("Temp file at {:?}", file.path());
-    write!(
-        file,
-        "Title: {title}
-Author: {author}
-Date: {date}
-ID: {post_id}
-
-Post body starts here
-",
-        title = title,
-        author = author,
-        post_id = post_id,
-        date = date,
-    )
-    .unwrap();
-    let mut proc = Command::new(editor)
-        .arg(file.path().to_str().unwrap())
-        .spawn()
-        .expect("Failed to execute parser command");
-    proc.wait().unwrap();
-    file.seek(SeekFrom::Start(0)).unwrap();
-    let mod_time = metadata(&file.path()).unwrap().modified().unwrap();
-    let buf = BufReader::new(file);
-    let pf = PostFile::new_from_buf(Box::new(buf), Some(mod_time), None).unwrap();
-    let date = chrono::DateTime::parse_from_rfc2822(&pf.get_header("date").unwrap()).unwrap();
-    let out_fname = Path::new(&conf.get_str("paths.post_dname").unwrap())
-        .join(&date.year().to_string())
+    let archetype = load_archetype(
+        &conf.get_str("paths.archetype_dname").unwrap(),
+        kind.as_deref(),
+    )?;
+    // Authors go in the header by key when there's a registry, so renaming someone is one edit
+    let registry = Authors::from_config(&conf)?;
+    let mut author_keys = vec![];
+    for a in &authors {
+        if registry.is_empty() {
+            author_keys.push(a.clone());
+            continue;
+        }
+        match registry.find(a) {
+            Some(author) => author_keys.push(author.key.clone()),
+            None => {
+                return Err(format!(
+                    "Unknown author '{}', expected one of {}",
+                    a,
+                    registry.keys().join(", ")
+                ))
+            }
+        }
+    }
+    let placeholders = Placeholders {
+        title,
+        author: if author_keys.is_empty() {
+            conf.get_str("strings.blog_author").unwrap()
+        } else {
+            author_keys.join(", ")
+        },
+        date,
+        id: post_id,
+        tags: tags.clone(),
+    };
+    let mut skeleton = fill(&archetype, &placeholders).map_err(|e| e.to_string())?;
+    // Explicitly given values win over whatever the archetype says
+    match author_keys.len() {
+        0 => {}
+        1 => skeleton
+            .set_header("Author", author_keys.remove(0))
+            .map_err(|e| e.to_string())?,
+        _ => skeleton
+            .set_header("Author", author_keys)
+            .map_err(|e| e.to_string())?,
+    }
+    if !tags.is_empty() {
+        let mut all_tags = skeleton.tags();
+        for tag in tags {
+            if !all_tags.contains(&tag) {
+                all_tags.push(tag);
+            }
+        }
+        skeleton
+            .set_header("Tags", all_tags)
+            .map_err(|e| e.to_string())?;
+    }
+    if let Some(body) = body {
+        skeleton.set_body(&body).map_err(|e| e.to_string())?;
+    }
+    let validate = |text: &str| -> Result<PostFile, String> {
+        let buf = Box::new(Cursor::new(text.as_bytes().to_vec()));
+        let pf = PostFile::new_from_buf(buf, None, None).map_err(|e| e.to_string())?;
+        if existing_ids.contains(&pf.id()) {
+            return Err(format!(
+                "ID {} is already used by another post in {}",
+                pf.id(),
+                post_dname
+            ));
+        }
+        Ok(pf)
+    };
+    let pf = if no_edit {
+        validate(&skeleton.to_string())?
+    } else {
+        let editor = resolve_editor(
+            &conf.get_str("paths.editor_bin").unwrap(),
+            std::env::var("VISUAL").ok(),
+            std::env::var("EDITOR").ok(),
+        )?;
+        let file = tempfile::Builder::new()
+            .prefix("reb-")
+            .suffix(".reb")
+            .tempfile()
+            .map_err(|e| e.to_string())?;
+        debug!("Temp file at {:?}", file.path());
+        match edit_until_valid(&editor, file.path(), &skeleton.to_string(), validate)? {
+            Some(pf) => pf,
+            None => return Err("Post was left unchanged or emptied, so it was not saved".into()),
+        }
+    };
+    let date = pf.date();
+    let out_fname = Path::new(&post_dname)
+        .join(date.year().to_string())
         .join(&if date.month() < 10 {
             format!("0{}", date.month())
         } else {
             date.month().to_string()
         })
-        .join(pf.get_suggested_source_filename());
+        .join(pf.get_suggested_source_filename(&get_slug_opts(&conf)));
     info!("Saving post to {}", out_fname.to_str().unwrap());
     create_dir_all(out_fname.parent().unwrap()).unwrap();
     let mut fd = OpenOptions::new()
-        .create(true)
         .write(true)
-        .truncate(true)
+        .create_new(true)
         .open(&out_fname)
-        .unwrap();
-    write!(fd, "{}", pf.to_string()).unwrap();
+        .map_err(|e| format!("{}: {}", out_fname.display(), e))?;
+    write!(fd, "{}", pf).unwrap();
     Ok(())
 }
 
-fn get_config() -> Result<Config, String> {
-    let mut conf = Config::new();
-    let ok = conf.merge(ConfigFile::with_name("src/config.default.toml"));
-    if ok.is_err() {
-        return Err(ok.unwrap_err().to_string());
+fn check(args: Args, conf: Config) -> Result<(), String> {
+    trace!("Calling check with {:?}", args);
+    let (deny_warnings, links, external) = match args.cmd {
+        CommandArgs::Check {
+            deny_warnings,
+            links,
+            external,
+        } => (deny_warnings, links, external),
+        _ => unreachable!(),
+    };
+    let mut diags = check_config_paths(Path::new(CONFIG_FNAME), &conf);
+    let mut posts = vec![];
+    for (key, page) in &[("paths.post_dname", false), ("paths.pages_dname", true)] {
+        let fnames = recursive_find_files(&conf.get_str(key).unwrap());
+        let mut fnames = paths_with_extension(&fnames, ".reb");
+        fnames.sort();
+        for fname in fnames {
+            match load_post(&fname, *page) {
+                Ok(pf) => {
+                    diags.extend(check_post(&fname, &pf));
+                    posts.push((fname, pf));
+                }
+                Err(d) => diags.push(d),
+            }
+        }
     }
-    Ok(conf)
+    diags.extend(check_post_set(&posts, &get_slug_opts(&conf)));
+    let pages: Vec<&PostFile> = posts
+        .iter()
+        .map(|(_, pf)| pf)
+        .filter(|pf| pf.kind() == Kind::Page)
+        .collect();
+    if let Err(e) = menu_from_config(&conf, &pages, &get_slug_opts(&conf)) {
+        diags.push(Diagnostic::new(
+            Path::new(CONFIG_FNAME),
+            None,
+            Level::Error,
+            e,
+        ));
+    }
+    let default_lang = conf.get_str("lang.default").unwrap().to_lowercase();
+    diags.extend(check_series(&posts, &default_lang));
+    diags.extend(check_translations(&posts, &default_lang));
+    match Authors::from_config(&conf) {
+        Ok(authors) => diags.extend(check_authors(&posts, &authors)),
+        Err(e) => diags.push(Diagnostic::new(
+            Path::new(CONFIG_FNAME),
+            None,
+            Level::Error,
+            e,
+        )),
+    }
+    let site = match SiteUrl::from_config(&conf) {
+        Ok(site) => Some(site),
+        Err(e) => {
+            diags.push(Diagnostic::new(
+                Path::new(CONFIG_FNAME),
+                None,
+                Level::Error,
+                e,
+            ));
+            None
+        }
+    };
+    let mut external_links = BTreeMap::new();
+    if let (true, Some(site)) = (links, site) {
+        let build_dname = PathBuf::from(conf.get_str("paths.build_dname").unwrap());
+        let sources = post_sources(posts.iter().map(|(_, pf)| pf), &get_slug_opts(&conf));
+        let report = check_links(&build_dname, &site, &sources);
+        diags.extend(report.broken);
+        external_links = report.external;
+    }
+    for d in &diags {
+        println!("{}", d);
+    }
+    if external && !external_links.is_empty() {
+        println!("Links to other sites, not checked:");
+        for (url, pages) in &external_links {
+            println!("{}", url);
+            for page in pages {
+                println!("    on {}", page.display());
+            }
+        }
+    }
+    let num_errors = diags
+        .iter()
+        .filter(|d| deny_warnings || d.level == Level::Error)
+        .count();
+    if num_errors > 0 {
+        return Err(format!("{} problem(s) found in the blog", num_errors));
+    }
+    info!("Checked {} post(s), no problems found", posts.len());
+    Ok(())
 }
 
-fn search_path(exe: &Path) -> Option<PathBuf> {
-    std::env::var_os("PATH").and_then(|paths| {
-        std::env::split_paths(&paths)
-            .filter_map(|dir| {
-                let p = dir.join(&exe);
-                if p.is_file() {
-                    Some(p)
-                } else {
-                    None
-                }
-            })
-            .next()
-    })
+/// Where the page for *pf* goes, relative to the build directory
+fn output_fname(pf: &PostFile, slug_opts: &SlugOpts) -> PathBuf {
+    match pf.kind() {
+        Kind::Post => Path::new("posts").join(pf.get_long_rendered_filename(slug_opts)),
+        Kind::Page => {
+            let path = pf.page_path(slug_opts);
+            let fname = Path::new(path.trim_start_matches('/')).to_path_buf();
+            if path.ends_with('/') {
+                fname.join("index.html")
+            } else {
+                fname
+            }
+        }
+    }
+}
+
+/// Where the files in the bundle of *pf* go: next to the page of a post, or in the directory of a
+/// standalone page
+fn bundle_url(pf: &PostFile, slug_opts: &SlugOpts) -> String {
+    match pf.kind() {
+        Kind::Post => "/posts/".to_string() + &pf.get_long_rendered_dirname(slug_opts),
+        Kind::Page => {
+            let path = pf.page_path(slug_opts);
+            path[..path.rfind('/').unwrap_or(0)].to_string()
+        }
+    }
+}
+
+/// The source file of each post in *posts*, by the path of its page relative to the build directory
+fn post_sources<'a>(
+    posts: impl Iterator<Item = &'a PostFile>,
+    slug_opts: &SlugOpts,
+) -> HashMap<PathBuf, PathBuf> {
+    posts
+        .filter_map(|pf| {
+            let page = output_fname(pf, slug_opts);
+            pf.fname().map(|fname| (page, fname.to_path_buf()))
+        })
+        .collect()
+}
+
+fn get_slug_opts(conf: &Config) -> SlugOpts {
+    SlugOpts {
+        max_len: conf.get_int("slug.max_len").unwrap() as usize,
+        max_words: conf.get_int("slug.max_words").unwrap() as usize,
+    }
+}
+
+fn get_image_opts(conf: &Config) -> ImageOpts {
+    ImageOpts {
+        widths: conf
+            .get_array("images.widths")
+            .unwrap()
+            .into_iter()
+            .map(|w| w.into_int().unwrap() as u32)
+            .collect(),
+        sizes: conf.get_str("images.sizes").unwrap(),
+        jpeg_quality: conf.get_int("images.jpeg_quality").unwrap() as u8,
+        cache_dname: Path::new(&conf.get_str("paths.cache_dname").unwrap()).join("images"),
+    }
+}
+
+fn get_highlight_opts(conf: &Config) -> HighlightOpts {
+    HighlightOpts {
+        enabled: conf.get_bool("highlight.enabled").unwrap(),
+        line_numbers: conf.get_bool("highlight.line_numbers").unwrap(),
+        theme: conf.get_str("highlight.theme").unwrap(),
+    }
+}
+
+fn get_reading_opts(conf: &Config) -> ReadingOpts {
+    ReadingOpts {
+        enabled: conf.get_bool("reading.enabled").unwrap(),
+        words_per_minute: conf.get_int("reading.words_per_minute").unwrap() as usize,
+        cjk_chars_per_minute: conf.get_int("reading.cjk_chars_per_minute").unwrap() as usize,
+    }
+}
+
+/// The footer config, with the years in the copyright going from the oldest of *posts* to the
+/// newest
+fn get_footer_opts(conf: &Config, posts: &[PostFile]) -> FooterOpts {
+    let years = posts.iter().map(|pf| pf.date().year());
+    let copyright = match (years.clone().min(), years.max()) {
+        (Some(first), Some(last)) if conf.get_bool("footer.copyright").unwrap() => {
+            let years = if first == last {
+                first.to_string()
+            } else {
+                format!("{}–{}", first, last)
+            };
+            Some(format!(
+                "© {} {}",
+                years,
+                conf.get_str("strings.blog_author").unwrap()
+            ))
+        }
+        _ => None,
+    };
+    FooterOpts {
+        copyright,
+        license: conf.get_str("footer.license").unwrap(),
+        license_url: conf.get_str("footer.license_url").unwrap(),
+        feed_links: conf.get_bool("footer.feed_links").unwrap(),
+        text: conf.get_str("footer.text").unwrap(),
+    }
+}
+
+const CONFIG_FNAME: &str = "src/config.default.toml";
+
+fn get_config() -> Result<Config, String> {
+    let mut conf = Config::new();
+    conf.merge(ConfigFile::with_name(CONFIG_FNAME))
+        .map_err(|e| e.to_string())?;
+    Ok(conf)
 }
 
 // Only returns Ok(..) if the config is well-formed.
 fn normalize_config(conf: &mut Config) -> Result<(), String> {
-    // Find various executables. First search in the current working directory, then fall back to
-    // searching the PATH
-    for key in ["paths.parse_bin", "paths.editor_bin"].iter() {
-        let value = conf.get_str(key).unwrap();
-        let s = Path::new(&value);
-        let final_s = if s.is_file() {
-            // If it exists in the current directory, use that
-            String::from("./") + s.to_str().unwrap()
-        } else {
-            // Otherwise search path
-            let s = search_path(s);
-            if s.is_none() {
-                return Err(format!("Could not find {} for key={} in PATH", value, key));
-            }
-            s.unwrap().to_str().unwrap().to_string()
-        };
-        debug!("Found {:?} for {}", final_s, key);
-        conf.set::<String>(key, final_s).unwrap();
-    }
+    // Find the parser. First search in the current working directory, then fall back to searching
+    // the PATH. The editor is only needed by reb create, which finds it itself.
+    let key = "paths.parse_bin";
+    let value = conf.get_str(key).unwrap();
+    let s = Path::new(&value);
+    let final_s = if s.is_file() {
+        // If it exists in the current directory, use that
+        String::from("./") + s.to_str().unwrap()
+    } else {
+        // Otherwise search path
+        match search_path(s) {
+            Some(s) => s.to_str().unwrap().to_string(),
+            None => return Err(format!("Could not find {} for key={} in PATH", value, key)),
+        }
+    };
+    debug!("Found {:?} for {}", final_s, key);
+    conf.set::<String>(key, final_s).unwrap();
     Ok(())
 }
 
@@ -378,23 +1533,23 @@ fn ensure_dirs(conf: &Config) -> Result<(), String> {
         conf.get_str("paths.build_dname").unwrap() + "/static/img",
     ];
     for d in &dnames {
-        let meta = metadata(d);
-        if meta.is_err() {
-            let meta = meta.unwrap_err();
-            if meta.kind() == std::io::ErrorKind::NotFound {
-                debug!("Making directory {}", d);
-                match create_dir_all(d) {
-                    Ok(_) => {}
-                    Err(e) => {
-                        return Err(e.to_string());
-                    }
-                };
-            } else {
-                err.push(meta.to_string());
+        let meta = match metadata(d) {
+            Ok(meta) => meta,
+            Err(meta) => {
+                if meta.kind() == std::io::ErrorKind::NotFound {
+                    debug!("Making directory {}", d);
+                    match create_dir_all(d) {
+                        Ok(_) => {}
+                        Err(e) => {
+                            return Err(e.to_string());
+                        }
+                    };
+                } else {
+                    err.push(meta.to_string());
+                }
+                continue;
             }
-            continue;
-        }
-        let meta = meta.unwrap();
+        };
         if meta.is_file() {
             err.push(format!("{} must be a directory, but is a file", d));
         }
@@ -409,6 +1564,10 @@ fn ensure_dirs(conf: &Config) -> Result<(), String> {
 fn main() -> Result<(), String> {
     env_logger::init();
     let args = Args::from_args();
+    // check only looks at the blog as it is, without changing anything
+    if let CommandArgs::Check { .. } = args.cmd {
+        return check(args, get_config()?);
+    }
     let mut conf = get_config()?;
     normalize_config(&mut conf)?;
     ensure_dirs(&conf)?;
@@ -417,5 +1576,6 @@ fn main() -> Result<(), String> {
         CommandArgs::Init { .. } => init(args, conf),
         CommandArgs::Build { .. } => build(args, conf),
         CommandArgs::Create { .. } => create(args, conf),
+        _ => unreachable!(),
     }
 }
@@ -0,0 +1,219 @@
+use config::{Config, Value as ConfigValue};
+
+use crate::post::file::{File as PostFile, BUNDLE_INDEX_FNAME};
+use crate::util::slug::SlugOpts;
+
+/// One link in the site's navigation
+#[derive(Debug, Clone, PartialEq)]
+pub struct MenuItem {
+    pub label: String,
+    /// A site path, or a URL of another site
+    pub url: String,
+    /// Where it goes in the menu, lowest first
+    pub order: i64,
+}
+impl MenuItem {
+    /// Whether this links to the page at site path *page*, so it can be shown as the current one
+    pub fn is_current(&self, page: &str) -> bool {
+        let without_index = |p: &str| p.trim_end_matches("index.html").to_string();
+        self.url.starts_with('/') && without_index(&self.url) == without_index(page)
+    }
+}
+
+/// An entry in the `[[menu]]` list in the config, before the page it names is looked up
+#[derive(Debug)]
+struct MenuEntry {
+    label: Option<String>,
+    url: Option<String>,
+    page: Option<String>,
+    order: Option<i64>,
+}
+
+fn parse_entry(value: ConfigValue) -> Result<MenuEntry, String> {
+    let mut table = value.into_table().map_err(|e| e.to_string())?;
+    let mut get_str = |k: &str| -> Result<Option<String>, String> {
+        match table.remove(k) {
+            Some(v) => v.into_str().map(Some).map_err(|e| format!("{}: {}", k, e)),
+            None => Ok(None),
+        }
+    };
+    let entry = MenuEntry {
+        label: get_str("label")?,
+        url: get_str("url")?,
+        page: get_str("page")?,
+        order: match table.remove("order") {
+            Some(v) => Some(v.into_int().map_err(|e| format!("order: {}", e))?),
+            None => None,
+        },
+    };
+    if let Some(k) = table.keys().next() {
+        return Err(format!("unknown key '{}'", k));
+    }
+    match (&entry.url, &entry.page, &entry.label) {
+        (Some(_), Some(_), _) => Err("has both a url and a page".to_string()),
+        (None, None, _) => Err("needs a url or a page".to_string()),
+        (Some(_), None, None) => Err("label is missing".to_string()),
+        _ => Ok(entry),
+    }
+}
+
+/// The name a `[[menu]]` entry uses for *pf*: its file name without the extension, or the name of
+/// its directory if it's a bundle
+fn page_name(pf: &PostFile) -> Option<String> {
+    let fname = pf.fname()?;
+    let name = if fname.file_name() == Some(BUNDLE_INDEX_FNAME.as_ref()) {
+        fname.parent()?.file_name()?
+    } else {
+        fname.file_stem()?
+    };
+    Some(name.to_string_lossy().into_owned())
+}
+
+/// The site's navigation: each entry in the `[[menu]]` list of *conf*, and then each of *pages*
+/// with a `Nav-Order` header that no entry already links to. Entries have a `label` and either a
+/// `url` or the `page` they link to, named like its file without the extension, which gives them
+/// its title as a label and its `Nav-Order` as their `order` unless they say otherwise. Everything
+/// is sorted by `order`, and entries without one keep their place in the list at 0.
+pub fn menu_from_config(
+    conf: &Config,
+    pages: &[&PostFile],
+    slug_opts: &SlugOpts,
+) -> Result<Vec<MenuItem>, String> {
+    let entries = conf.get_array("menu").unwrap_or_default();
+    let mut items = vec![];
+    let mut linked = vec![];
+    for (i, value) in entries.into_iter().enumerate() {
+        let err = |e: String| format!("menu entry {}: {}", i + 1, e);
+        let entry = parse_entry(value).map_err(err)?;
+        let item = match (entry.url, entry.page) {
+            (Some(url), _) => MenuItem {
+                label: entry.label.unwrap(),
+                url,
+                order: entry.order.unwrap_or(0),
+            },
+            (None, Some(name)) => {
+                let pf = pages
+                    .iter()
+                    .find(|pf| page_name(pf).as_ref() == Some(&name))
+                    .ok_or_else(|| err(format!("no page named '{}'", name)))?;
+                linked.push(pf.page_path(slug_opts));
+                MenuItem {
+                    label: entry.label.unwrap_or_else(|| pf.title()),
+                    url: pf.page_path(slug_opts),
+                    order: entry.order.or_else(|| pf.nav_order()).unwrap_or(0),
+                }
+            }
+            (None, None) => unreachable!(),
+        };
+        items.push(item);
+    }
+    let mut nav_pages: Vec<&&PostFile> = pages
+        .iter()
+        .filter(|pf| pf.nav_order().is_some() && !linked.contains(&pf.page_path(slug_opts)))
+        .collect();
+    nav_pages.sort_by_key(|pf| (pf.nav_order(), pf.title()));
+    items.extend(nav_pages.into_iter().map(|pf| MenuItem {
+        label: pf.title(),
+        url: pf.page_path(slug_opts),
+        order: pf.nav_order().unwrap(),
+    }));
+    items.sort_by_key(|item| item.order);
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{menu_from_config, MenuItem};
+    use crate::post::file::{File as PostFile, FileOpts};
+    use crate::util::slug::SlugOpts;
+    use config::{Config, File as ConfigFile, FileFormat};
+    use std::fs::{create_dir_all, write};
+
+    fn conf(toml: &str) -> Config {
+        let mut conf = Config::new();
+        conf.merge(ConfigFile::from_str(toml, FileFormat::Toml))
+            .unwrap();
+        conf
+    }
+
+    #[test]
+    fn menu() {
+        let opts = SlugOpts {
+            max_len: 64,
+            max_words: 8,
+        };
+        let dir = tempfile::tempdir().unwrap();
+        create_dir_all(dir.path().join("contact")).unwrap();
+        write(
+            dir.path().join("about.reb"),
+            "Title: About me\nNav-Order: 5\n\nBody",
+        )
+        .unwrap();
+        write(
+            dir.path().join("contact/index.reb"),
+            "Title: Contact\nPath: /contact.html\nNav-Order: 9\n\nBody",
+        )
+        .unwrap();
+        write(dir.path().join("colophon.reb"), "Title: Colophon\n\nBody").unwrap();
+        let pages: Vec<PostFile> = ["about.reb", "contact/index.reb", "colophon.reb"]
+            .iter()
+            .map(|f| {
+                let opts = FileOpts {
+                    strict_headers: true,
+                    page: true,
+                };
+                PostFile::new_from_file(&dir.path().join(f), Some(opts)).unwrap()
+            })
+            .collect();
+        let pages: Vec<&PostFile> = pages.iter().collect();
+
+        // Without a menu, pages with a Nav-Order are in it
+        let items = menu_from_config(&conf(""), &pages, &opts).unwrap();
+        let urls: Vec<&str> = items.iter().map(|i| i.url.as_str()).collect();
+        assert_eq!(urls, vec!["/about-me/", "/contact.html"]);
+
+        let items = menu_from_config(
+            &conf(
+                "[[menu]]
+label = \"Home\"
+url = \"/\"
+
+[[menu]]
+page = \"contact\"
+order = 1
+
+[[menu]]
+label = \"Source\"
+url = \"https://example.com/blog\"
+order = 20
+",
+            ),
+            &pages,
+            &opts,
+        )
+        .unwrap();
+        let labels: Vec<&str> = items.iter().map(|i| i.label.as_str()).collect();
+        assert_eq!(labels, vec!["Home", "Contact", "About me", "Source"]);
+        assert!(items[0].is_current("/index.html"));
+        assert!(!items[0].is_current("/posts/a.html"));
+        assert!(items[1].is_current("/contact.html"));
+        assert!(!items[3].is_current("/"));
+        assert_eq!(
+            items[2],
+            MenuItem {
+                label: "About me".to_string(),
+                url: "/about-me/".to_string(),
+                order: 5,
+            }
+        );
+
+        for toml in &[
+            "[[menu]]\npage = \"missing\"",
+            "[[menu]]\nurl = \"/\"",
+            "[[menu]]\nlabel = \"A\"\nurl = \"/\"\npage = \"about\"",
+            "[[menu]]\nlabel = \"A\"\nurl = \"/\"\nweight = 2",
+        ] {
+            assert!(menu_from_config(&conf(toml), &pages, &opts).is_err());
+        }
+    }
+}
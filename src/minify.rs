@@ -0,0 +1,297 @@
+use crate::util::html::{tokenize, Token};
+
+/// Elements whose whitespace is shown as it is
+const PREFORMATTED_ELEMENTS: &[&str] = &["pre", "textarea"];
+
+/// Elements that always start on a new line, so whitespace between two of them is never shown
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "body",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hr",
+    "html",
+    "li",
+    "link",
+    "main",
+    "meta",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "script",
+    "section",
+    "style",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "title",
+    "tr",
+    "ul",
+];
+
+/// Whether whitespace next to *token* can go, because it's the edge of a block
+fn is_block_edge(token: Option<&Token<'_>>) -> bool {
+    match token {
+        None => true,
+        Some(Token::Tag(tag, _)) => BLOCK_ELEMENTS.contains(&tag.name.as_str()),
+        Some(Token::Other(_)) => true,
+        Some(Token::Text(_)) => false,
+    }
+}
+
+/// *s* with each run of whitespace made into one space, or one newline if it had any
+fn collapse_whitespace(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut run: Option<char> = None;
+    for c in s.chars() {
+        if c.is_ascii_whitespace() {
+            if c == '\n' || run.is_none() {
+                run = Some(if c == '\n' { '\n' } else { ' ' });
+            }
+            continue;
+        }
+        if let Some(ws) = run.take() {
+            out.push(ws);
+        }
+        out.push(c);
+    }
+    if let Some(ws) = run {
+        out.push(ws);
+    }
+    out
+}
+
+/// *html* without comments and with as little whitespace as shows the same page. Text in `<pre>`
+/// and `<textarea>` and scripts are left exactly as they are, and styles go through `minify_css`.
+/// Conditional comments like `<!--[if IE]>` are kept, since they aren't really comments.
+pub fn minify_html(html: &str) -> String {
+    let tokens: Vec<Token<'_>> = tokenize(html)
+        .into_iter()
+        .filter(|t| match t {
+            Token::Other(s) => !s.starts_with("<!--") || s.starts_with("<!--[if"),
+            _ => true,
+        })
+        .collect();
+    let mut out = String::with_capacity(html.len());
+    let mut preformatted = 0usize;
+    let mut raw: Option<&str> = None;
+    let mut i = 0;
+    while i < tokens.len() {
+        let before = i.checked_sub(1).map(|p| &tokens[p]);
+        match &tokens[i] {
+            Token::Tag(tag, source) => {
+                if PREFORMATTED_ELEMENTS.contains(&tag.name.as_str()) {
+                    if tag.closing {
+                        preformatted = preformatted.saturating_sub(1);
+                    } else if !tag.self_closing {
+                        preformatted += 1;
+                    }
+                }
+                raw = match tag.name.as_str() {
+                    "script" | "style" if !tag.closing => Some(tag.name.as_str()),
+                    _ => None,
+                };
+                out += source;
+            }
+            Token::Text(text) if raw == Some("style") => out += &minify_css(text),
+            Token::Text(text) if raw.is_some() || preformatted > 0 => out += text,
+            Token::Text(text) => {
+                // Text that was on both sides of a comment is handled as one
+                let mut text = text.to_string();
+                while let Some(Token::Text(more)) = tokens.get(i + 1) {
+                    text += more;
+                    i += 1;
+                }
+                let text = collapse_whitespace(&text);
+                let blank = text.trim().is_empty();
+                if !(blank && is_block_edge(before) && is_block_edge(tokens.get(i + 1))) {
+                    out += &text;
+                }
+            }
+            Token::Other(s) => out += s,
+        }
+        i += 1;
+    }
+    out
+}
+
+/// *css* without comments and with no more whitespace than it needs. Spaces go around `{`, `}`,
+/// `;`, `,` and `>` and after `:`, but stay before `:` since they matter in `a :hover`, and
+/// around `+` and `-` since they matter in `calc()`.
+pub fn minify_css(css: &str) -> String {
+    const PUNCTUATION: &[char] = &['{', '}', ';', ',', '>'];
+    let mut out = String::with_capacity(css.len());
+    let mut space = false;
+    let mut chars = css.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+                space = true;
+            }
+            c if c.is_ascii_whitespace() => space = true,
+            c => {
+                if space
+                    && !out.is_empty()
+                    && !out.ends_with(|p| PUNCTUATION.contains(&p) || p == ':')
+                    && !PUNCTUATION.contains(&c)
+                {
+                    out.push(' ');
+                }
+                space = false;
+                if c == '}' && out.ends_with(';') {
+                    out.pop();
+                }
+                out.push(c);
+                // Strings are kept as they are, whatever they have in them
+                if c == '"' || c == '\'' {
+                    let mut escaped = false;
+                    for s in chars.by_ref() {
+                        out.push(s);
+                        if s == c && !escaped {
+                            break;
+                        }
+                        escaped = s == '\\' && !escaped;
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Minifies pages and stylesheets on their way out of `build`, when that's turned on, and keeps
+/// count of how much smaller they got
+#[derive(Debug, Default)]
+pub struct Minifier {
+    enabled: bool,
+    files: usize,
+    before: usize,
+    after: usize,
+}
+impl Minifier {
+    pub fn new(enabled: bool) -> Self {
+        Minifier {
+            enabled,
+            ..Default::default()
+        }
+    }
+
+    fn count(&mut self, before: usize, after: &str) {
+        self.files += 1;
+        self.before += before;
+        self.after += after.len();
+    }
+
+    pub fn html(&mut self, html: &str) -> String {
+        if !self.enabled {
+            return html.to_string();
+        }
+        let out = minify_html(html);
+        self.count(html.len(), &out);
+        out
+    }
+
+    pub fn css(&mut self, css: &str) -> String {
+        if !self.enabled {
+            return css.to_string();
+        }
+        let out = minify_css(css);
+        self.count(css.len(), &out);
+        out
+    }
+
+    /// A line for the build summary, if anything was minified
+    pub fn summary(&self) -> Option<String> {
+        if self.files == 0 {
+            return None;
+        }
+        let saved = self.before.saturating_sub(self.after);
+        Some(format!(
+            "Minified {} file(s): {:.1} KB smaller ({:.1}%)",
+            self.files,
+            saved as f64 / 1024.0,
+            saved as f64 * 100.0 / self.before.max(1) as f64
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{minify_css, minify_html, Minifier};
+
+    #[test]
+    fn html() {
+        assert_eq!(
+            minify_html(
+                "<!DOCTYPE html>\n<html>\n  <body>\n    <!-- post_body -->\n    <p>Some   <em>very</em>\n\n   \
+                 good\ttext</p>\n<pre>  keep\n    this </pre>\n<textarea> and  this</textarea>\n\
+                 <script>if (a  <  b) {}</script>\n<style> p  { color: red; } </style>\n\
+                 <!--[if IE]><p>Old</p><![endif]-->\n</body>\n</html>\n"
+            ),
+            "<!DOCTYPE html><html><body><p>Some <em>very</em>\ngood text</p><pre>  keep\n    this </pre>\n\
+             <textarea> and  this</textarea>\n<script>if (a  <  b) {}</script><style>p{color:red}</style>\
+             <!--[if IE]><p>Old</p><![endif]--></body></html>"
+        );
+        // Whitespace between inline elements shows as a space
+        assert_eq!(
+            minify_html("<p><a href=\"#\">a</a> <b>b</b></p>"),
+            "<p><a href=\"#\">a</a> <b>b</b></p>"
+        );
+    }
+
+    #[test]
+    fn css() {
+        assert_eq!(
+            minify_css(
+                "/* Layout */\nbody {\n  margin: 0 auto;\n  font-family: \"Open  Sans\", sans-serif;\n}\n\
+                 a :hover, ul > li { width: calc(100% - 2em); content: '/* no */'; }\n"
+            ),
+            "body{margin:0 auto;font-family:\"Open  Sans\",sans-serif}\
+             a :hover,ul>li{width:calc(100% - 2em);content:'/* no */'}"
+        );
+    }
+
+    #[test]
+    fn summary() {
+        let mut off = Minifier::new(false);
+        assert_eq!(off.html("<p> a </p>\n"), "<p> a </p>\n");
+        assert_eq!(off.summary(), None);
+        let mut on = Minifier::new(true);
+        on.css("a { }");
+        assert_eq!(
+            on.summary().unwrap(),
+            "Minified 1 file(s): 0.0 KB smaller (40.0%)"
+        );
+    }
+}
@@ -1,19 +1,123 @@
-use super::header::HeaderLine;
+use super::frontmatter::{parse_toml, parse_yaml, render_toml, render_yaml};
+use super::header::{render_simple, HeaderLine};
+use super::value::Value;
 use super::PostParseError;
-use std::io::BufRead;
+use crate::util::slug::{slugify, slugify_with_limits, SlugOpts};
+use chrono::{DateTime, FixedOffset, TimeZone};
+use std::fmt;
+use std::fs;
+use std::io::{BufRead, BufReader, Cursor};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::time::SystemTime;
 
+/// Header keys whose values are dates
+pub const DATE_HEADERS: &[&str] = &["date", "modified"];
+
+/// Header keys that reb knows what to do with. Anything else is allowed, but `reb check` will
+/// point it out in case it is a typo.
+pub const KNOWN_HEADERS: &[&str] = &[
+    "title",
+    "author",
+    "date",
+    "id",
+    "modified",
+    "tags",
+    "status",
+    "slug",
+    "toc",
+    "series",
+    "series-part",
+    "lang",
+    "translation-of",
+    "description",
+    "image",
+    "type",
+    "path",
+    "nav-order",
+];
+
+/// A post can be a directory with this file in it, plus whatever images and other files it uses
+pub const BUNDLE_INDEX_FNAME: &str = "index.reb";
+
+const TOML_FENCE: &str = "+++";
+const YAML_FENCE: &str = "---";
+
+/// How the headers of a file are written
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum HeaderFormat {
+    /// `Key: value` lines
+    Simple,
+    /// Between `+++` lines, with the index of the closing one
+    Toml(usize),
+    /// Between `---` lines, with the index of the closing one
+    Yaml(usize),
+}
+
+/// Whether a post should be built. Set with the `Status` header, and published if not given.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Published,
+    Draft,
+}
+impl FromStr for Status {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "published" => Ok(Status::Published),
+            "draft" => Ok(Status::Draft),
+            _ => Err(format!("'{}' is not one of published, draft", s)),
+        }
+    }
+}
+
+/// What a file is, set with the `Type` header. Posts by default, but files in the pages directory
+/// are always pages.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// Dated, and shown in the index and feeds
+    Post,
+    /// Stands on its own at a path of its choosing, like an About page
+    Page,
+}
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "post" => Ok(Kind::Post),
+            "page" => Ok(Kind::Page),
+            _ => Err(format!("'{}' is not one of post, page", s)),
+        }
+    }
+}
+
 pub struct FileOpts {
     /// If true, then all required headers must be present and well-formed
     pub strict_headers: bool,
+    /// Read the file as a page whatever its `Type` header says
+    pub page: bool,
 }
 
 #[derive(Debug)]
 pub struct File {
     headers: Vec<HeaderLine>,
-    text: String, // All text in file
-    body: String, // Only after header and seperator
+    text: String,       // All text in file
+    body: String,       // Only after header and seperator
+    body_offset: usize, // Byte offset of body in text
+    format: HeaderFormat,
+    strict_headers: bool,
     last_modified: Option<u64>,
+    fname: Option<PathBuf>,
+    // Typed versions of well-known headers, filled in while parsing
+    date: Option<DateTime<FixedOffset>>,
+    modified: Option<DateTime<FixedOffset>>,
+    status: Status,
+    kind: Kind,
+    toc: Option<bool>,
+    series_part: Option<u32>,
+    nav_order: Option<i64>,
 }
 impl File {
     fn new() -> Self {
@@ -21,61 +125,310 @@ impl File {
             headers: vec![],
             text: String::new(),
             body: String::new(),
+            body_offset: 0,
+            format: HeaderFormat::Simple,
+            strict_headers: true,
             last_modified: None,
+            fname: None,
+            date: None,
+            modified: None,
+            status: Status::Published,
+            kind: Kind::Post,
+            toc: None,
+            series_part: None,
+            nav_order: None,
         }
     }
 
     pub fn new_from_buf(
-        buf: Box<BufRead>,
+        buf: Box<dyn BufRead>,
         last_modified: Option<SystemTime>,
         opts_in: Option<FileOpts>,
     ) -> Result<Self, PostParseError> {
         // If no FileOpts was given, set the default options
-        let opts = if opts_in.is_none() {
-            FileOpts {
-                strict_headers: true,
-            }
+        let opts = opts_in.unwrap_or(FileOpts {
+            strict_headers: true,
+            page: false,
+        });
+        let mut f = Self::new();
+        f.strict_headers = opts.strict_headers;
+        let mut buf = buf;
+        let mut text = String::new();
+        buf.read_to_string(&mut text)?;
+        // Keep line endings around so the body can be sliced out of the text byte for byte
+        let raw_lines: Vec<&str> = text.split_inclusive('\n').collect();
+        let lines: Vec<String> = raw_lines
+            .iter()
+            .map(|l| l.trim_end_matches('\n').trim_end_matches('\r').to_string())
+            .collect();
+        // Front matter fences, if any, must be the first thing in the file
+        let first = lines.iter().position(|l| {
+            let l = l.trim();
+            !l.is_empty() && !l.starts_with('#')
+        });
+        let body_start = match first {
+            Some(i) if lines[i].trim() == TOML_FENCE => f.parse_fenced(&lines, i, TOML_FENCE)?,
+            Some(i) if lines[i].trim() == YAML_FENCE => f.parse_fenced(&lines, i, YAML_FENCE)?,
+            _ => f.parse_simple(&lines)?,
+        };
+        f.body_offset = raw_lines[..body_start].iter().map(|l| l.len()).sum();
+        f.body = text[f.body_offset..].to_string();
+        f.text = text;
+        if let Some(last_modified) = last_modified {
+            f.set_last_modified(last_modified);
+        }
+        // Which headers are required depends on what the file is
+        f.kind = if opts.page {
+            Kind::Page
         } else {
-            opts_in.unwrap()
+            f.parse_kind(opts.strict_headers)?
         };
-        let mut f = Self::new();
-        let mut all_lines = vec![];
-        let mut body_lines = vec![];
-        let mut doing_headers: bool = true;
-        for line in buf.lines() {
-            let line = line?;
-            all_lines.push(line.clone());
-            if doing_headers {
-                let line = line.trim();
-                if line.starts_with('#') {
+        if opts.strict_headers {
+            f.has_required_headers()
+                .map_err(PostParseError::MissingHeaders)?;
+        }
+        f.parse_typed_headers(opts.strict_headers)?;
+        Ok(f)
+    }
+
+    /// Read the post in *fname*, remembering where it came from
+    pub fn new_from_file(fname: &Path, opts_in: Option<FileOpts>) -> Result<Self, PostParseError> {
+        let buf = BufReader::new(fs::File::open(fname)?);
+        let mod_time = fs::metadata(fname)?.modified().ok();
+        let mut f = Self::new_from_buf(Box::new(buf), mod_time, opts_in)?;
+        f.fname = Some(fname.to_path_buf());
+        Ok(f)
+    }
+
+    /// Parse `Key: value` headers, returning the index of the first body line
+    fn parse_simple(&mut self, lines: &[String]) -> Result<usize, PostParseError> {
+        // The line index a header started on, the index of its last line, and all of its lines
+        let mut pending: Option<(usize, usize, Vec<&str>)> = None;
+        let mut body_start = lines.len();
+        for (i, line) in lines.iter().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.starts_with('#') {
+                continue;
+            }
+            if trimmed.is_empty() {
+                if pending.is_none() && self.headers.is_empty() {
                     continue;
                 }
-                if line.is_empty() && !f.headers.is_empty() {
-                    doing_headers = false;
-                    continue;
-                } else if line.is_empty() {
-                    continue;
+                body_start = i + 1;
+                break;
+            }
+            // An indented line carries on the header before it, unless it's a header reb knows
+            let is_known_header = HeaderLine::new(trimmed)
+                .map(|h| KNOWN_HEADERS.contains(&h.key.to_lowercase().as_str()))
+                .unwrap_or(false);
+            let is_continuation =
+                (line.starts_with(' ') || line.starts_with('\t')) && !is_known_header;
+            match pending {
+                Some((_, ref mut last, ref mut v)) if is_continuation => {
+                    *last = i;
+                    v.push(line);
+                }
+                _ => {
+                    if let Some((start, last, v)) = pending.take() {
+                        self.push_simple_header(start, last, &v)?;
+                    }
+                    pending = Some((i, i, vec![trimmed]));
                 }
-                f.headers.push(HeaderLine::new(line)?);
-            } else {
-                body_lines.push(line);
             }
         }
-        f.text = all_lines.join("\n");
-        f.body = body_lines.join("\n");
-        if last_modified.is_some() {
-            f.set_last_modified(last_modified.unwrap());
+        if let Some((start, last, v)) = pending {
+            self.push_simple_header(start, last, &v)?;
         }
-        let err = if opts.strict_headers {
-            f.has_required_headers()
+        Ok(body_start)
+    }
+
+    fn push_simple_header(
+        &mut self,
+        idx: usize,
+        last: usize,
+        lines: &[&str],
+    ) -> Result<(), PostParseError> {
+        let mut h = HeaderLine::new(&lines.join("\n"))
+            .map_err(|e| PostParseError::AtLine(idx + 1, Box::new(e)))?;
+        h.line = idx + 1;
+        h.num_lines = last - idx + 1;
+        self.headers.push(h);
+        Ok(())
+    }
+
+    /// Parse a TOML or YAML front matter block whose opening *fence* is at index *start*,
+    /// returning the index of the first body line
+    fn parse_fenced(
+        &mut self,
+        lines: &[String],
+        start: usize,
+        fence: &str,
+    ) -> Result<usize, PostParseError> {
+        let end = match lines[start + 1..].iter().position(|l| l.trim() == fence) {
+            Some(i) => start + 1 + i,
+            None => {
+                return Err(PostParseError::AtLine(
+                    start + 1,
+                    Box::new(PostParseError::BadFrontMatter(format!(
+                        "no closing {} for front matter",
+                        fence
+                    ))),
+                ))
+            }
+        };
+        let block = &lines[start + 1..end];
+        // Line numbers are 1-based, and the block starts on the line after the fence
+        let first_line = start + 2;
+        let headers = if fence == TOML_FENCE {
+            parse_toml(block, first_line)
         } else {
-            Ok(())
+            parse_yaml(block, first_line)
         };
-        if err.is_err() {
-            Err(PostParseError::MissingHeaders(err.unwrap_err()))
+        self.headers = headers.map_err(|e| match e {
+            PostParseError::AtLine(..) => e,
+            e => PostParseError::AtLine(start + 1, Box::new(e)),
+        })?;
+        self.format = if fence == TOML_FENCE {
+            HeaderFormat::Toml(end)
         } else {
-            Ok(f)
+            HeaderFormat::Yaml(end)
+        };
+        // A header runs until the next one starts, minus any comments or blank lines in between
+        for i in 0..self.headers.len() {
+            let start = self.headers[i].line - 1;
+            let mut next = match self.headers.get(i + 1) {
+                Some(h) => h.line - 1,
+                None => end,
+            };
+            while next > start + 1 {
+                let l = lines[next - 1].trim();
+                if !l.is_empty() && !l.starts_with('#') {
+                    break;
+                }
+                next -= 1;
+            }
+            self.headers[i].num_lines = next - start;
+        }
+        // Like with simple headers, a blank line may separate the front matter from the body
+        match lines.get(end + 1) {
+            Some(l) if l.trim().is_empty() => Ok(end + 2),
+            _ => Ok(end + 1),
+        }
+    }
+
+    /// What the `Type` header says the file is. If *strict*, a type that isn't known is an error.
+    /// Otherwise the file is a post.
+    fn parse_kind(&self, strict: bool) -> Result<Kind, PostParseError> {
+        match self.find_header("type") {
+            Some(h) => match h.value.to_string().parse::<Kind>() {
+                Ok(kind) => Ok(kind),
+                Err(e) if strict => Err(PostParseError::AtLine(
+                    h.line,
+                    Box::new(PostParseError::InvalidHeader(h.key.clone(), e)),
+                )),
+                Err(_) => Ok(Kind::Post),
+            },
+            None => Ok(Kind::Post),
+        }
+    }
+
+    /// Fill in the typed versions of well-known headers. If *strict*, a header that is present but
+    /// can't be understood is an error. Otherwise it is ignored.
+    fn parse_typed_headers(&mut self, strict: bool) -> Result<(), PostParseError> {
+        let invalid = |h: &HeaderLine, why: String| {
+            PostParseError::AtLine(
+                h.line,
+                Box::new(PostParseError::InvalidHeader(h.key.clone(), why)),
+            )
+        };
+        let date_header = |key: &str| match self.find_header(key) {
+            Some(h) => match h.value.as_date() {
+                Some(d) => Ok(Some(d)),
+                None if strict => Err(invalid(h, format!("'{}' is not a valid date", h.value))),
+                None => Ok(None),
+            },
+            None => Ok(None),
+        };
+        let date = date_header(DATE_HEADERS[0])?;
+        let modified = date_header(DATE_HEADERS[1])?;
+        let status = match self.find_header("status") {
+            Some(h) => match h.value.to_string().parse::<Status>() {
+                Ok(status) => status,
+                Err(e) if strict => return Err(invalid(h, e)),
+                Err(_) => Status::Published,
+            },
+            None => Status::Published,
+        };
+        let toc = match self.find_header("toc") {
+            Some(h) => match h.value.as_bool() {
+                Some(b) => Some(b),
+                None if strict => {
+                    return Err(invalid(h, format!("'{}' is not true or false", h.value)))
+                }
+                None => None,
+            },
+            None => None,
+        };
+        let series_part = match self.find_header("series-part") {
+            Some(h) => match h.value.as_int() {
+                Some(i) if i > 0 && i <= i64::from(u32::MAX) => Some(i as u32),
+                _ if strict => {
+                    return Err(invalid(
+                        h,
+                        format!("'{}' is not a part number starting at 1", h.value),
+                    ))
+                }
+                _ => None,
+            },
+            None => None,
+        };
+        let nav_order = match self.find_header("nav-order") {
+            Some(h) => match h.value.as_int() {
+                Some(i) => Some(i),
+                None if strict => {
+                    return Err(invalid(h, format!("'{}' is not a number", h.value)));
+                }
+                None => None,
+            },
+            None => None,
+        };
+        // A page's path ends up in the build directory, so it can't go anywhere outside of it
+        if let Some(h) = self.find_header("path") {
+            let path = h.value.to_string();
+            let valid = path.starts_with('/')
+                && !path.split('/').any(|p| p == "." || p == "..")
+                && path
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "/-_.".contains(c));
+            if !valid && strict {
+                return Err(invalid(
+                    h,
+                    format!(
+                        "'{}' is not a path from the root of the site, like /about/",
+                        h.value
+                    ),
+                ));
+            }
+        }
+        // The language ends up in URLs, so it has to look like a language code
+        if let Some(h) = self.find_header("lang") {
+            let lang = h.value.to_string();
+            let valid = !lang.trim().is_empty()
+                && lang
+                    .trim()
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-');
+            if !valid && strict {
+                return Err(invalid(h, format!("'{}' is not a language code", h.value)));
+            }
         }
+        self.date = date;
+        self.modified = modified;
+        self.status = status;
+        self.toc = toc;
+        self.series_part = series_part;
+        self.nav_order = nav_order;
+        Ok(())
     }
 
     fn set_last_modified(&mut self, last_modified: SystemTime) {
@@ -88,18 +441,31 @@ impl File {
     }
 
     pub fn get_header(&self, key: &str) -> Option<String> {
+        self.find_header(key).map(|h| h.value.to_string())
+    }
+
+    /// The typed value of the first header with the given key
+    pub fn get_value(&self, key: &str) -> Option<&Value> {
+        self.find_header(key).map(|h| &h.value)
+    }
+
+    /// The first header line with the given key, compared case-insensitively
+    pub fn find_header(&self, key: &str) -> Option<&HeaderLine> {
         let key = key.to_lowercase();
-        for h in &self.headers {
-            if h.key.to_lowercase() == key {
-                return Some(h.value.clone());
-            }
-        }
-        None
+        self.headers.iter().find(|h| h.key.to_lowercase() == key)
+    }
+
+    /// All header lines, in the order they appear in the file
+    pub fn headers(&self) -> &[HeaderLine] {
+        &self.headers
     }
 
     pub fn has_required_headers(&self) -> Result<(), String> {
         let mut missing = vec![];
-        let required_headers = ["title", "author", "id", "date"];
+        let required_headers: &[&str] = match self.kind {
+            Kind::Post => &["title", "author", "id", "date"],
+            Kind::Page => &["title"],
+        };
         for h in required_headers.iter() {
             if !self.has_header(h) {
                 missing.push(*h);
@@ -112,6 +478,218 @@ impl File {
         }
     }
 
+    pub fn title(&self) -> String {
+        self.get_header("title").unwrap_or_default()
+    }
+
+    /// Everyone who wrote the post. `Author: Ann, Bob` and `author = ["Ann", "Bob"]` both give two.
+    pub fn authors(&self) -> Vec<String> {
+        match self.get_value("author") {
+            Some(v) => v.as_list(),
+            None => vec![],
+        }
+    }
+
+    pub fn id(&self) -> String {
+        self.get_header("id").unwrap_or_default()
+    }
+
+    /// When the post was written. Always present in posts parsed with strict headers, and the
+    /// Unix epoch otherwise if the post doesn't have a valid date.
+    pub fn date(&self) -> DateTime<FixedOffset> {
+        self.date
+            .unwrap_or_else(|| FixedOffset::east(0).timestamp(0, 0))
+    }
+
+    /// When the post was last updated, if it says so
+    pub fn modified(&self) -> Option<DateTime<FixedOffset>> {
+        self.modified
+    }
+
+    pub fn tags(&self) -> Vec<String> {
+        match self.get_value("tags") {
+            Some(v) => v.as_list(),
+            None => vec![],
+        }
+    }
+
+    /// Whether the post is published or a draft. Nothing leaves drafts out of the build yet.
+    #[allow(dead_code)]
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    /// Where a page goes, as a path from the root of the site. That's its `Path` header if it has
+    /// one, and a directory named after its slug if not.
+    pub fn page_path(&self, opts: &SlugOpts) -> String {
+        match self.get_header("path") {
+            Some(path) => path.trim().to_string(),
+            None => format!("/{}/", self.slug(opts)),
+        }
+    }
+
+    /// Where a page goes in the site's navigation, if it's there at all
+    pub fn nav_order(&self) -> Option<i64> {
+        self.nav_order
+    }
+
+    /// Whether the `TOC` header asks for a table of contents, if the post has one
+    pub fn toc(&self) -> Option<bool> {
+        self.toc
+    }
+
+    /// The language the post is written in, lowercased, if the `Lang` header says
+    pub fn lang(&self) -> Option<String> {
+        self.get_header("lang")
+            .map(|s| s.trim().to_lowercase())
+            .filter(|s| !s.is_empty())
+    }
+
+    /// The ID of the post this one is a translation of, if it is one
+    pub fn translation_of(&self) -> Option<String> {
+        self.get_header("translation-of")
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    }
+
+    /// What the post is about in a sentence or two, for search engines and link previews
+    pub fn description(&self) -> Option<String> {
+        self.get_header("description")
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    }
+
+    /// The image shown in link previews, as a URL, a path in the static directory or a file in
+    /// the post's bundle
+    pub fn image(&self) -> Option<String> {
+        self.get_header("image")
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    }
+
+    /// The name of the series of posts this one is part of, if any
+    pub fn series(&self) -> Option<String> {
+        self.get_header("series")
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    }
+
+    /// Where this post goes in its series, if the `Series-Part` header says
+    pub fn series_part(&self) -> Option<u32> {
+        self.series_part
+    }
+
+    /// Set the value of the header *key*, replacing the first header with that key if there is
+    /// one and adding a new header after the others if not. Everything else in the file is left
+    /// exactly as it was, and the file is parsed again so the change can be seen right away.
+    pub fn set_header<V: Into<Value>>(
+        &mut self,
+        key: &str,
+        value: V,
+    ) -> Result<(), PostParseError> {
+        let value = value.into();
+        let mut lines = self.raw_lines();
+        let (start, end, key) = match self.find_header(key) {
+            // Keep the existing key so its casing doesn't change
+            Some(h) => (h.line - 1, h.line - 1 + h.num_lines, h.key.clone()),
+            None => {
+                let idx = match self.format {
+                    HeaderFormat::Toml(end) | HeaderFormat::Yaml(end) => end,
+                    HeaderFormat::Simple => match self.headers.last() {
+                        Some(h) => h.line - 1 + h.num_lines,
+                        None => 0,
+                    },
+                };
+                (idx, idx, key.to_string())
+            }
+        };
+        let mut new_lines = match self.format {
+            HeaderFormat::Simple => render_simple(&key, &value),
+            HeaderFormat::Toml(_) => vec![render_toml(&key, &value)],
+            HeaderFormat::Yaml(_) => vec![render_yaml(&key, &value)],
+        };
+        // The first header of a file goes before its body, with a blank line to keep them apart
+        if self.format == HeaderFormat::Simple && self.headers.is_empty() && !self.body.is_empty() {
+            new_lines.push(String::new());
+        }
+        let nl = self.newline();
+        if start > 0 && !lines[start - 1].ends_with('\n') {
+            lines[start - 1] += nl;
+        }
+        lines.splice(start..end, new_lines.into_iter().map(|l| l + nl));
+        self.reparse(lines.concat())
+    }
+
+    /// Remove every header with the given key, along with any continuation lines
+    pub fn remove_header(&mut self, key: &str) -> Result<(), PostParseError> {
+        let key = key.to_lowercase();
+        let mut lines = self.raw_lines();
+        let mut removed = false;
+        // Go backwards so earlier line numbers stay valid
+        for h in self.headers.iter().rev() {
+            if h.key.to_lowercase() == key {
+                lines.drain(h.line - 1..h.line - 1 + h.num_lines);
+                removed = true;
+            }
+        }
+        if !removed {
+            return Ok(());
+        }
+        self.reparse(lines.concat())
+    }
+
+    /// Replace the body with *body*, byte for byte
+    pub fn set_body(&mut self, body: &str) -> Result<(), PostParseError> {
+        let mut text = self.text[..self.body_offset].to_string();
+        if !body.is_empty() {
+            let nl = self.newline();
+            if !text.is_empty() && !text.ends_with('\n') {
+                text += nl;
+            }
+            // Make sure there's a blank line between the headers and the body
+            if !text.is_empty() && !text.ends_with("\n\n") && !text.ends_with("\n\r\n") {
+                text += nl;
+            }
+        }
+        text += body;
+        self.reparse(text)
+    }
+
+    /// Lines of the file with their line endings, so joining them gives back the file exactly
+    fn raw_lines(&self) -> Vec<String> {
+        self.text
+            .split_inclusive('\n')
+            .map(|l| l.to_string())
+            .collect()
+    }
+
+    /// The line ending this file uses
+    fn newline(&self) -> &'static str {
+        if self.text.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        }
+    }
+
+    /// Replace this file with *text* after parsing it with the same options it was parsed with
+    fn reparse(&mut self, text: String) -> Result<(), PostParseError> {
+        let buf = Box::new(Cursor::new(text.into_bytes()));
+        let opts = FileOpts {
+            strict_headers: self.strict_headers,
+            page: self.kind == Kind::Page,
+        };
+        let mut f = Self::new_from_buf(buf, None, Some(opts))?;
+        f.last_modified = self.last_modified;
+        f.fname = self.fname.take();
+        *self = f;
+        Ok(())
+    }
+
     pub fn has_header(&self, key: &str) -> bool {
         self.get_header(key).is_some()
     }
@@ -120,45 +698,69 @@ impl File {
         self.body.clone()
     }
 
+    /// The file this post was read from, if it was read from one
+    pub fn fname(&self) -> Option<&Path> {
+        self.fname.as_deref()
+    }
+
+    /// The directory holding this post and its files, if it is a bundle
+    pub fn bundle_dname(&self) -> Option<&Path> {
+        match self.fname() {
+            Some(fname) if fname.file_name() == Some(BUNDLE_INDEX_FNAME.as_ref()) => fname.parent(),
+            _ => None,
+        }
+    }
+
     //pub fn get_last_modified(&self) -> Option<u64> {
     //    self.last_modified
     //}
 
-    fn hyphenated_title_for_filename(&self, len: usize) -> String {
-        self.get_header("title")
-            .unwrap()
-            .to_lowercase()
-            .split(' ')
-            .collect::<Vec<&str>>()[0..len]
-            .join("-")
+    /// The part of the filename that comes from the title, or from the `Slug` header if there is
+    /// one. Explicit slugs are cleaned up but not shortened.
+    pub fn slug(&self, opts: &SlugOpts) -> String {
+        let slug = match self.get_header("slug") {
+            Some(slug) => slugify(&slug),
+            None => slugify_with_limits(&self.title(), opts),
+        };
+        if slug.is_empty() {
+            "post".to_string()
+        } else {
+            slug
+        }
+    }
+
+    pub fn get_long_rendered_filename(&self, opts: &SlugOpts) -> String {
+        self.get_long_rendered_dirname(opts) + ".html"
     }
 
-    pub fn get_long_rendered_filename(&self) -> String {
-        let mut s = self.hyphenated_title_for_filename(3);
+    /// Where the files of a bundle go, next to the rendered post
+    pub fn get_long_rendered_dirname(&self, opts: &SlugOpts) -> String {
+        let mut s = self.slug(opts);
         s += "-";
-        s += &self.get_header("id").unwrap();
-        s += ".html";
+        s += &self.id();
         s
     }
 
-    pub fn get_suggested_source_filename(&self) -> String {
-        let mut s = self.hyphenated_title_for_filename(3);
+    pub fn get_suggested_source_filename(&self, opts: &SlugOpts) -> String {
+        let mut s = self.slug(opts);
         s += "-";
-        s += &self.get_header("id").unwrap();
+        s += &self.id();
         s += ".reb";
         s
     }
 }
-impl ToString for File {
-    fn to_string(&self) -> String {
-        self.text.clone()
+impl fmt::Display for File {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{File, FileOpts};
-    use std::io::BufReader;
+    use super::{File, FileOpts, Kind, Status};
+    use crate::post::PostParseError;
+    use crate::util::slug::SlugOpts;
+    use std::io::{BufReader, Cursor};
 
     #[test]
     fn first_line_blank() {
@@ -169,6 +771,7 @@ mod tests {
             None,
             Some(FileOpts {
                 strict_headers: false,
+                page: false,
             }),
         )
         .unwrap();
@@ -188,6 +791,7 @@ mod tests {
             None,
             Some(FileOpts {
                 strict_headers: false,
+                page: false,
             }),
         );
         assert!(err.is_err());
@@ -202,16 +806,343 @@ mod tests {
             None,
             Some(FileOpts {
                 strict_headers: false,
+                page: false,
             }),
         )
         .unwrap();
         assert_eq!(pf.headers.len(), 1);
-        for key in vec!["aaaa", "AAAA", "Aaaa", "aAaA"] {
+        for key in &["aaaa", "AAAA", "Aaaa", "aAaA"] {
             assert!(pf.has_header(key));
             assert_eq!(pf.get_header(key).unwrap(), "bbbb");
         }
     }
 
+    #[test]
+    fn bad_header_line_number() {
+        let text = "# comment\nAaaa: bbbb\nnot a header\n\nHi There";
+        let br = BufReader::new(text.as_bytes());
+        match File::new_from_buf(Box::new(br), None, None) {
+            Err(PostParseError::AtLine(line, _)) => assert_eq!(line, 3),
+            _ => panic!("expected an error on line 3"),
+        }
+    }
+
+    #[test]
+    fn header_line_numbers() {
+        let text = "\nAaaa: bbbb\n# comment\nCccc: dddd\n\nHi There";
+        let br = BufReader::new(text.as_bytes());
+        let pf = File::new_from_buf(
+            Box::new(br),
+            None,
+            Some(FileOpts {
+                strict_headers: false,
+                page: false,
+            }),
+        )
+        .unwrap();
+        assert_eq!(pf.find_header("aaaa").unwrap().line, 2);
+        assert_eq!(pf.find_header("cccc").unwrap().line, 4);
+    }
+
+    #[test]
+    fn continuation_lines() {
+        let text = "Title: A\nSummary: One\n  Two\n  Author: B\nID: x\nDescription: More\n  \
+                    onto a line that says see: https://example.org\n\n  Indented body";
+        let br = BufReader::new(text.as_bytes());
+        let pf = File::new_from_buf(
+            Box::new(br),
+            None,
+            Some(FileOpts {
+                strict_headers: false,
+                page: false,
+            }),
+        )
+        .unwrap();
+        assert_eq!(pf.headers.len(), 5);
+        assert_eq!(pf.get_header("summary").unwrap(), "One\nTwo");
+        // An indented header is still a header of its own, if reb knows it
+        assert_eq!(pf.get_header("author").unwrap(), "B");
+        assert_eq!(
+            pf.get_header("description").unwrap(),
+            "More\nonto a line that says see: https://example.org"
+        );
+        assert_eq!(pf.find_header("id").unwrap().line, 5);
+        assert_eq!(pf.body, "  Indented body");
+    }
+
+    #[test]
+    fn toml_front_matter() {
+        let text = "+++\ntitle = \"A\"\nauthor = \"B\"\nid = \"x\"\n\
+                    date = 2018-10-18T10:00:00Z\ntags = [\"a\", \"b\"]\n+++\n\nBody";
+        let br = BufReader::new(text.as_bytes());
+        let pf = File::new_from_buf(Box::new(br), None, None).unwrap();
+        assert_eq!(pf.get_header("title").unwrap(), "A");
+        assert_eq!(pf.get_header("tags").unwrap(), "a, b");
+        assert!(pf.get_value("date").unwrap().as_date().is_some());
+        assert_eq!(pf.find_header("tags").unwrap().line, 6);
+        assert_eq!(pf.body, "Body");
+        assert_eq!(pf.to_string(), text);
+    }
+
+    #[test]
+    fn yaml_front_matter() {
+        let text = "---\ntitle: A\nauthor: B\nid: x\ndate: 2018-10-18\n---\nBody";
+        let br = BufReader::new(text.as_bytes());
+        let pf = File::new_from_buf(Box::new(br), None, None).unwrap();
+        assert_eq!(pf.get_header("author").unwrap(), "B");
+        assert_eq!(pf.body, "Body");
+    }
+
+    #[test]
+    fn unclosed_front_matter() {
+        let text = "\n---\ntitle: A\n\nBody";
+        let br = BufReader::new(text.as_bytes());
+        match File::new_from_buf(Box::new(br), None, None) {
+            Err(PostParseError::AtLine(line, _)) => assert_eq!(line, 2),
+            _ => panic!("expected an error on line 2"),
+        }
+    }
+
+    #[test]
+    fn typed_accessors() {
+        let text = "Title: A\nAuthor: B\nID: x\nDate: Thu, 18 Oct 2018 10:00:00 +0000\n\
+                    Modified: 2018-10-20\nTags: rust, blogging\nStatus: Draft\nTOC: yes\n\
+                    Series: Learning Rust \nSeries-Part: 2\nLang: de-AT\nTranslation-Of: y\n\
+                    Description: All about A. \nImage: img/a.png\n\nBody";
+        let br = BufReader::new(text.as_bytes());
+        let pf = File::new_from_buf(Box::new(br), None, None).unwrap();
+        assert_eq!(pf.title(), "A");
+        assert_eq!(pf.authors(), vec!["B"]);
+        assert_eq!(pf.id(), "x");
+        assert_eq!(pf.date().to_rfc3339(), "2018-10-18T10:00:00+00:00");
+        assert_eq!(
+            pf.modified().unwrap().to_rfc3339(),
+            "2018-10-20T00:00:00+00:00"
+        );
+        assert_eq!(pf.tags(), vec!["rust", "blogging"]);
+        assert_eq!(pf.status(), Status::Draft);
+        assert_eq!(pf.toc(), Some(true));
+        assert_eq!(pf.series(), Some("Learning Rust".to_string()));
+        assert_eq!(pf.series_part(), Some(2));
+        assert_eq!(pf.lang(), Some("de-at".to_string()));
+        assert_eq!(pf.translation_of(), Some("y".to_string()));
+        assert_eq!(pf.description(), Some("All about A.".to_string()));
+        assert_eq!(pf.image(), Some("img/a.png".to_string()));
+    }
+
+    #[test]
+    fn pages() {
+        let opts = SlugOpts {
+            max_len: 64,
+            max_words: 8,
+        };
+        // Pages only need a title
+        let text = "Title: About me\nType: page\nNav-Order: 2\n\nBody";
+        let br = BufReader::new(text.as_bytes());
+        let pf = File::new_from_buf(Box::new(br), None, None).unwrap();
+        assert_eq!(pf.kind(), Kind::Page);
+        assert_eq!(pf.page_path(&opts), "/about-me/");
+        assert_eq!(pf.nav_order(), Some(2));
+        let text = "Title: Contact\nPath: /contact.html\n\nBody";
+        let br = BufReader::new(text.as_bytes());
+        let opts_page = FileOpts {
+            strict_headers: true,
+            page: true,
+        };
+        let pf = File::new_from_buf(Box::new(br), None, Some(opts_page)).unwrap();
+        assert_eq!(pf.kind(), Kind::Page);
+        assert_eq!(pf.page_path(&opts), "/contact.html");
+        // Posts still need everything
+        let br = BufReader::new("Title: A\n\nBody".as_bytes());
+        assert!(File::new_from_buf(Box::new(br), None, None).is_err());
+        for text in &[
+            "Title: A\nType: pgae\n\nBody",
+            "Title: A\nType: page\nPath: ../etc/\n\nBody",
+            "Title: A\nType: page\nNav-Order: first\n\nBody",
+        ] {
+            let br = BufReader::new(text.as_bytes());
+            match File::new_from_buf(Box::new(br), None, None) {
+                Err(PostParseError::AtLine(2, _)) | Err(PostParseError::AtLine(3, _)) => {}
+                r => panic!("expected an error in {:?}, got {:?}", text, r),
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_typed_headers() {
+        for (text, bad_line, bad_key) in &[
+            (
+                "Title: A\nAuthor: B\nID: x\nDate: yesterday\n\nBody",
+                4,
+                "Date",
+            ),
+            (
+                "Title: A\nAuthor: B\nID: x\nDate: 2018-10-18\nStatus: gone\n\nBody",
+                5,
+                "Status",
+            ),
+            (
+                "Title: A\nAuthor: B\nID: x\nDate: 2018-10-18\nTOC: maybe\n\nBody",
+                5,
+                "TOC",
+            ),
+            (
+                "Title: A\nAuthor: B\nID: x\nDate: 2018-10-18\nSeries-Part: 0\n\nBody",
+                5,
+                "Series-Part",
+            ),
+            (
+                "Title: A\nAuthor: B\nID: x\nDate: 2018-10-18\nLang: ../de\n\nBody",
+                5,
+                "Lang",
+            ),
+        ] {
+            let br = BufReader::new(text.as_bytes());
+            match File::new_from_buf(Box::new(br), None, None) {
+                Err(PostParseError::AtLine(line, e)) => {
+                    assert_eq!(line, *bad_line);
+                    match *e {
+                        PostParseError::InvalidHeader(key, _) => assert_eq!(key, *bad_key),
+                        _ => panic!("expected an invalid header"),
+                    }
+                }
+                _ => panic!("expected an error on line {}", bad_line),
+            }
+        }
+    }
+
+    fn non_strict(text: &str) -> File {
+        let br = BufReader::new(Cursor::new(text.to_string().into_bytes()));
+        File::new_from_buf(
+            Box::new(br),
+            None,
+            Some(FileOpts {
+                strict_headers: false,
+                page: false,
+            }),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn round_trip_untouched() {
+        for text in &[
+            "# c\nTitle: A\n\nBody\n\n",
+            "Title: A\r\nSummary: x\r\n  y\r\n\r\nBody\r\n",
+            "+++\ntitle = \"A\"\n+++\nBody",
+            "Title: A",
+        ] {
+            let pf = non_strict(text);
+            assert_eq!(pf.to_string(), *text);
+        }
+    }
+
+    #[test]
+    fn set_header_simple() {
+        let mut pf =
+            non_strict("# comment\nTITLE: Old\nSummary: a\n  b\nID: x\n\nBody\n  with bytes\n");
+        pf.set_header("title", "New").unwrap();
+        pf.set_header("summary", "c\nd\ne").unwrap();
+        pf.set_header("Tags", vec!["x".to_string(), "y".to_string()])
+            .unwrap();
+        assert_eq!(
+            pf.to_string(),
+            "# comment\nTITLE: New\nSummary: c\n  d\n  e\nID: x\nTags: x, y\n\nBody\n  with bytes\n"
+        );
+        assert_eq!(pf.get_header("title").unwrap(), "New");
+        assert_eq!(pf.tags(), vec!["x", "y"]);
+        assert_eq!(pf.find_header("id").unwrap().line, 6);
+    }
+
+    #[test]
+    fn set_header_no_trailing_newline() {
+        let mut pf = non_strict("Title: A");
+        pf.set_header("ID", "x").unwrap();
+        assert_eq!(pf.to_string(), "Title: A\nID: x\n");
+        let mut pf = non_strict("\r\nTitle: A\r\n\r\nBody");
+        pf.set_header("ID", "x").unwrap();
+        assert_eq!(pf.to_string(), "\r\nTitle: A\r\nID: x\r\n\r\nBody");
+    }
+
+    #[test]
+    fn set_header_headerless() {
+        let mut pf = non_strict("");
+        pf.set_header("Title", "A").unwrap();
+        assert_eq!(pf.to_string(), "Title: A\n");
+        pf.set_body("Body\n").unwrap();
+        assert_eq!(pf.to_string(), "Title: A\n\nBody\n");
+        // Comments stay with the headers
+        let mut pf = non_strict("# notes\n\n");
+        pf.set_header("Title", "A").unwrap();
+        pf.set_header("ID", "x").unwrap();
+        assert_eq!(pf.to_string(), "Title: A\nID: x\n# notes\n\n");
+        assert_eq!(non_strict(&pf.to_string()).to_string(), pf.to_string());
+        assert_eq!(pf.get_body(), "");
+    }
+
+    #[test]
+    fn remove_header() {
+        let mut pf = non_strict("Title: A\nTags: a\n  b\n# keep me\nID: x\ntags: c\n\nBody");
+        pf.remove_header("TAGS").unwrap();
+        assert_eq!(pf.to_string(), "Title: A\n# keep me\nID: x\n\nBody");
+        pf.remove_header("nope").unwrap();
+        assert_eq!(pf.to_string(), "Title: A\n# keep me\nID: x\n\nBody");
+    }
+
+    #[test]
+    fn set_body() {
+        let mut pf = non_strict("Title: A\n\nOld body\n");
+        pf.set_body("New\n\nbody").unwrap();
+        assert_eq!(pf.to_string(), "Title: A\n\nNew\n\nbody");
+        assert_eq!(pf.get_body(), "New\n\nbody");
+        let mut pf = non_strict("Title: A");
+        pf.set_body("Body\n").unwrap();
+        assert_eq!(pf.to_string(), "Title: A\n\nBody\n");
+        assert_eq!(pf.get_body(), "Body\n");
+    }
+
+    #[test]
+    fn set_header_front_matter() {
+        let mut pf = non_strict(
+            "+++\n# comment\nTitle = \"A\"\ntags = [\n  \"a\",\n]\n\nid = \"x\"\n+++\n\nBody",
+        );
+        pf.set_header("tags", vec!["b".to_string()]).unwrap();
+        pf.set_header("title", "B").unwrap();
+        pf.set_header("draft", true).unwrap();
+        assert_eq!(
+            pf.to_string(),
+            "+++\n# comment\nTitle = \"B\"\ntags = [\"b\"]\n\nid = \"x\"\ndraft = true\n+++\n\nBody"
+        );
+        let mut pf = non_strict("---\ntitle: A\nid: x\n---\nBody");
+        pf.set_header("title", "B: the sequel").unwrap();
+        pf.remove_header("id").unwrap();
+        assert_eq!(pf.to_string(), "---\ntitle: \"B: the sequel\"\n---\nBody");
+        assert_eq!(pf.title(), "B: the sequel");
+    }
+
+    #[test]
+    fn filenames() {
+        let opts = SlugOpts {
+            max_len: 50,
+            max_words: 6,
+        };
+        let mut pf = non_strict("Title: Hi!\nID: x\n\nBody");
+        assert_eq!(pf.get_long_rendered_filename(&opts), "hi-x.html");
+        pf.set_header("Title", "Ünïcode: a/b").unwrap();
+        assert_eq!(
+            pf.get_suggested_source_filename(&opts),
+            "uenicode-a-b-x.reb"
+        );
+        pf.set_header("Title", "???").unwrap();
+        assert_eq!(pf.slug(&opts), "post");
+        pf.set_header("Slug", "My Own Slug, which is long enough to not be cut")
+            .unwrap();
+        assert_eq!(
+            pf.slug(&opts),
+            "my-own-slug-which-is-long-enough-to-not-be-cut"
+        );
+    }
+
     #[test]
     fn missing_header() {
         let text = "";
@@ -221,6 +1152,7 @@ mod tests {
             None,
             Some(FileOpts {
                 strict_headers: false,
+                page: false,
             }),
         )
         .unwrap();
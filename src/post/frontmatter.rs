@@ -0,0 +1,289 @@
+use super::file::DATE_HEADERS;
+use super::header::HeaderLine;
+use super::value::{parse_date, Value};
+use super::PostParseError;
+use yaml_rust::{Yaml, YamlLoader};
+
+/// Find the line that defines the top-level *key* in a front matter block, so header line numbers
+/// point somewhere useful. *sep* is `=` for TOML and `:` for YAML.
+fn find_key_line(lines: &[String], first_line: usize, key: &str, sep: char) -> (usize, String) {
+    for (i, line) in lines.iter().enumerate() {
+        if line.starts_with(' ') || line.starts_with('\t') {
+            continue;
+        }
+        let k = match line.find(sep) {
+            Some(idx) => line[..idx].trim().trim_matches(|c| c == '"' || c == '\''),
+            None => continue,
+        };
+        if k == key {
+            return (first_line + i, line.clone());
+        }
+    }
+    (first_line, format!("{}{} ...", key, sep))
+}
+
+fn from_toml(key: &str, v: toml::Value) -> Result<Value, PostParseError> {
+    Ok(match v {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::Integer(i),
+        toml::Value::Float(f) => Value::Float(f),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(d) => match parse_date(&d.to_string()) {
+            Some(d) => Value::Date(d),
+            None => {
+                return Err(PostParseError::BadFrontMatter(format!(
+                    "{}: dates need at least a day, not just a time",
+                    key
+                )))
+            }
+        },
+        toml::Value::Array(a) => Value::List(
+            a.into_iter()
+                .map(|v| from_toml(key, v))
+                .collect::<Result<Vec<Value>, PostParseError>>()?,
+        ),
+        toml::Value::Table(_) => {
+            return Err(PostParseError::BadFrontMatter(format!(
+                "{}: tables are not supported",
+                key
+            )))
+        }
+    })
+}
+
+/// Parse the lines between `+++` fences. *first_line* is the 1-based line number of the first of
+/// *lines* in the file.
+pub fn parse_toml(lines: &[String], first_line: usize) -> Result<Vec<HeaderLine>, PostParseError> {
+    let table = match lines.join("\n").parse::<toml::Value>() {
+        Ok(toml::Value::Table(t)) => t,
+        Ok(_) => {
+            return Err(PostParseError::BadFrontMatter(
+                "TOML front matter must be a table".to_string(),
+            ))
+        }
+        Err(e) => return Err(PostParseError::BadFrontMatter(e.to_string())),
+    };
+    let mut headers = vec![];
+    for (key, value) in table {
+        let (line, text) = find_key_line(lines, first_line, &key, '=');
+        let value =
+            from_toml(&key, value).map_err(|e| PostParseError::AtLine(line, Box::new(e)))?;
+        headers.push(HeaderLine::from_parts(&text, &key, value, line));
+    }
+    // TOML tables don't remember their order, but the file does
+    headers.sort_by_key(|h| h.line);
+    Ok(headers)
+}
+
+fn from_yaml(key: &str, v: Yaml) -> Result<Option<Value>, PostParseError> {
+    Ok(Some(match v {
+        // YAML has no date type of its own, so the values of date headers are dates if they look
+        // like one. Any other string is kept as it is, even if it looks like a date.
+        Yaml::String(s) => match parse_date(&s) {
+            Some(d) if DATE_HEADERS.contains(&key.to_lowercase().as_str()) => Value::Date(d),
+            _ => Value::String(s),
+        },
+        Yaml::Integer(i) => Value::Integer(i),
+        Yaml::Real(r) => match r.parse() {
+            Ok(f) => Value::Float(f),
+            Err(_) => {
+                return Err(PostParseError::BadFrontMatter(format!(
+                    "{}: '{}' is not a number",
+                    key, r
+                )))
+            }
+        },
+        Yaml::Boolean(b) => Value::Bool(b),
+        Yaml::Array(a) => {
+            let mut out = vec![];
+            for v in a {
+                if let Some(v) = from_yaml(key, v)? {
+                    out.push(v);
+                }
+            }
+            Value::List(out)
+        }
+        Yaml::Null => return Ok(None),
+        _ => {
+            return Err(PostParseError::BadFrontMatter(format!(
+                "{}: only strings, numbers, booleans, dates and lists are supported",
+                key
+            )))
+        }
+    }))
+}
+
+/// Parse the lines between `---` fences. *first_line* is the 1-based line number of the first of
+/// *lines* in the file.
+pub fn parse_yaml(lines: &[String], first_line: usize) -> Result<Vec<HeaderLine>, PostParseError> {
+    let docs = YamlLoader::load_from_str(&lines.join("\n"))
+        .map_err(|e| PostParseError::BadFrontMatter(e.to_string()))?;
+    let hash = match docs.into_iter().next() {
+        Some(Yaml::Hash(h)) => h,
+        None => return Ok(vec![]),
+        Some(_) => {
+            return Err(PostParseError::BadFrontMatter(
+                "YAML front matter must be a mapping".to_string(),
+            ))
+        }
+    };
+    let mut headers = vec![];
+    for (key, value) in hash {
+        let key = match key {
+            Yaml::String(s) => s,
+            Yaml::Integer(i) => i.to_string(),
+            _ => {
+                return Err(PostParseError::BadFrontMatter(
+                    "YAML keys must be strings".to_string(),
+                ))
+            }
+        };
+        let (line, text) = find_key_line(lines, first_line, &key, ':');
+        let value =
+            from_yaml(&key, value).map_err(|e| PostParseError::AtLine(line, Box::new(e)))?;
+        if let Some(value) = value {
+            headers.push(HeaderLine::from_parts(&text, &key, value, line));
+        }
+    }
+    Ok(headers)
+}
+
+/// Quote *s* as a double-quoted string, which means the same thing in TOML and YAML
+fn quote(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            '\n' => out += "\\n",
+            '\t' => out += "\\t",
+            '\r' => out += "\\r",
+            c if c.is_control() => out += &format!("\\u{:04X}", c as u32),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// A value as it would appear on the right hand side of a TOML or YAML key. Lists use the inline
+/// `[a, b]` syntax, which both formats understand.
+fn render_inline(value: &Value) -> String {
+    match value {
+        Value::String(s) => quote(s),
+        Value::List(v) => format!(
+            "[{}]",
+            v.iter()
+                .map(render_inline)
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) => format!("{:?}", f),
+        Value::Bool(b) => b.to_string(),
+        Value::Date(d) => d.to_rfc3339(),
+    }
+}
+
+/// Write *key* and *value* as a single line of TOML front matter
+pub fn render_toml(key: &str, value: &Value) -> String {
+    format!("{} = {}", key, render_inline(value))
+}
+
+/// Write *key* and *value* as a single line of YAML front matter
+pub fn render_yaml(key: &str, value: &Value) -> String {
+    format!("{}: {}", key, render_inline(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_toml, parse_yaml, render_toml, render_yaml};
+    use crate::post::value::Value;
+
+    fn lines(s: &str) -> Vec<String> {
+        s.lines().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn toml_types() {
+        let h = parse_toml(
+            &lines(
+                "title = \"Hello\"
+tags = [\"a\", \"b\"]
+date = 2018-10-18T10:00:00Z
+draft = true
+weight = 3",
+            ),
+            2,
+        )
+        .unwrap();
+        let keys: Vec<&str> = h.iter().map(|h| h.key.as_str()).collect();
+        assert_eq!(keys, vec!["title", "tags", "date", "draft", "weight"]);
+        assert_eq!(h[0].value, "Hello");
+        assert_eq!(h[0].line, 2);
+        assert_eq!(h[1].value.to_string(), "a, b");
+        assert_eq!(
+            h[2].value.as_date().unwrap().to_rfc3339(),
+            "2018-10-18T10:00:00+00:00"
+        );
+        assert_eq!(h[3].value, Value::Bool(true));
+        assert_eq!(h[4].value, Value::Integer(3));
+        assert_eq!(h[4].line, 6);
+    }
+
+    #[test]
+    fn yaml_types() {
+        let h = parse_yaml(
+            &lines(
+                "title: Hello
+summary: |
+  Line one
+  line two
+tags:
+  - a
+  - b
+date: 2018-10-18
+ratio: 0.5
+title_date: 2019-05-01",
+            ),
+            2,
+        )
+        .unwrap();
+        assert_eq!(h[0].value, "Hello");
+        assert_eq!(h[1].value, "Line one\nline two\n");
+        assert_eq!(h[2].value.to_string(), "a, b");
+        assert_eq!(h[2].line, 6);
+        assert!(h[3].value.as_date().is_some());
+        assert_eq!(h[4].value, Value::Float(0.5));
+        // Only date headers are dates
+        assert_eq!(h[5].value, Value::String("2019-05-01".to_string()));
+    }
+
+    #[test]
+    fn render_round_trip() {
+        let values = vec![
+            Value::String("A \"quoted\"\nmulti-line string".to_string()),
+            Value::List(vec![
+                Value::String("a".to_string()),
+                Value::String("b".to_string()),
+            ]),
+            Value::Float(1.0),
+            Value::Bool(false),
+            Value::Date(crate::post::value::parse_date("2018-10-18T10:00:00+02:00").unwrap()),
+        ];
+        for v in values {
+            let h = parse_toml(&[render_toml("date", &v)], 1).unwrap();
+            assert_eq!(h[0].value, v);
+            let h = parse_yaml(&[render_yaml("date", &v)], 1).unwrap();
+            assert_eq!(h[0].value, v);
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(parse_toml(&lines("title = "), 2).is_err());
+        assert!(parse_toml(&lines("[table]\na = 1"), 2).is_err());
+        assert!(parse_yaml(&lines("- a\n- b"), 2).is_err());
+        assert!(parse_yaml(&lines("ratio: .inf"), 2).is_err());
+    }
+}
@@ -1,39 +1,77 @@
+use super::value::Value;
 use super::PostParseError;
+use std::fmt;
 
 #[derive(Debug)]
 pub struct HeaderLine {
     text: String,
     pub key: String,
-    pub value: String,
+    pub value: Value,
+    /// 1-based line number in the source file, or 0 if unknown
+    pub line: usize,
+    /// How many lines of the source file this header takes up, starting at *line*
+    pub num_lines: usize,
 }
 impl HeaderLine {
+    /// Parse a simple `Key: value` header. Any lines after the first are continuation lines, and
+    /// their trimmed contents are added to the value one per line.
     pub fn new(text: &str) -> Result<Self, PostParseError> {
-        let colon_idx = text.find(':');
-        if colon_idx.is_none() {
-            return Err(PostParseError::NotAHeader(text.to_string()));
-        }
-        let colon_idx = colon_idx.unwrap();
-        let key = &text[0..colon_idx].trim();
-        let value = &text[colon_idx + 1..].trim();
+        let mut lines = text.lines();
+        let first = lines.next().unwrap_or("");
+        let colon_idx = match first.find(':') {
+            Some(i) => i,
+            None => return Err(PostParseError::NotAHeader(text.to_string())),
+        };
+        let key = &first[0..colon_idx].trim();
+        let mut value = vec![first[colon_idx + 1..].trim()];
+        value.extend(lines.map(|l| l.trim()));
+        let value = value.join("\n");
+        let value = value.trim();
         if key.is_empty() || value.is_empty() {
             return Err(PostParseError::NotAHeader(text.to_string()));
         }
         Ok(Self {
             text: text.to_string(),
             key: key.to_string(),
-            value: value.to_string(),
+            value: Value::String(value.to_string()),
+            line: 0,
+            num_lines: 1,
         })
     }
+
+    /// A header that came from TOML or YAML front matter, where *text* is its source line
+    pub fn from_parts(text: &str, key: &str, value: Value, line: usize) -> Self {
+        Self {
+            text: text.to_string(),
+            key: key.to_string(),
+            value,
+            line,
+            num_lines: 1,
+        }
+    }
 }
-impl ToString for HeaderLine {
-    fn to_string(&self) -> String {
-        self.text.clone()
+
+/// Write a simple `Key: value` header, using continuation lines if the value has more than one
+/// line. Blank lines would end the headers, so they are dropped. Lines after the first that look
+/// like a header reb knows, such as `Author: B`, are read back as headers of their own, so values
+/// like that need front matter.
+pub fn render_simple(key: &str, value: &Value) -> Vec<String> {
+    let value = value.to_string();
+    let mut lines = value.lines().map(|l| l.trim()).filter(|l| !l.is_empty());
+    let mut out = vec![format!("{}: {}", key, lines.next().unwrap_or(""))];
+    out.extend(lines.map(|l| format!("  {}", l)));
+    out
+}
+impl fmt::Display for HeaderLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::HeaderLine;
+    use super::{render_simple, HeaderLine};
+    use crate::post::value::Value;
 
     #[test]
     fn trimming() {
@@ -41,7 +79,7 @@ mod tests {
             assert_eq!(h.key, "aaaa");
             assert_eq!(h.value, "bbbb");
         };
-        for text in vec!["aaaa:bbbb", "aaaa: bbbb", " aaaa : bbbb "] {
+        for text in &["aaaa:bbbb", "aaaa: bbbb", " aaaa : bbbb "] {
             let h = HeaderLine::new(text).unwrap();
             test(h);
         }
@@ -54,9 +92,28 @@ mod tests {
         assert_eq!(h.value, "Soup Four");
     }
 
+    #[test]
+    fn continuation() {
+        let h = HeaderLine::new("Summary: First line\n  second line\n\tthird").unwrap();
+        assert_eq!(h.key, "Summary");
+        assert_eq!(h.value, "First line\nsecond line\nthird");
+        let h = HeaderLine::new("Summary:\n  only line").unwrap();
+        assert_eq!(h.value, "only line");
+        assert!(HeaderLine::new("Summary\n  a: b").is_err());
+    }
+
+    #[test]
+    fn render() {
+        let v = Value::String("One\n\nTwo".to_string());
+        let lines = render_simple("Summary", &v);
+        assert_eq!(lines, vec!["Summary: One", "  Two"]);
+        let h = HeaderLine::new(&lines.join("\n")).unwrap();
+        assert_eq!(h.value, "One\nTwo");
+    }
+
     #[test]
     fn invalid() {
-        for text in vec!["nocolon", "", ":", "a:", ":b"] {
+        for text in &["nocolon", "", ":", "a:", ":b"] {
             let h = HeaderLine::new(text);
             assert!(h.is_err());
         }
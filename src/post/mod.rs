@@ -1,5 +1,7 @@
 pub mod file;
+mod frontmatter;
 mod header;
+pub mod value;
 
 use std::fmt;
 
@@ -8,6 +10,11 @@ pub enum PostParseError {
     IOError(std::io::Error),
     MissingHeaders(String),
     NotAHeader(String),
+    BadFrontMatter(String),
+    /// A well-known header with a value that doesn't make sense, like a date that can't be parsed
+    InvalidHeader(String, String),
+    /// Some other error that happened on the given (1-based) line
+    AtLine(usize, Box<PostParseError>),
 }
 impl fmt::Display for PostParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -15,6 +22,9 @@ impl fmt::Display for PostParseError {
             PostParseError::IOError(ref e) => format!("IOError: {}", e),
             PostParseError::MissingHeaders(ref e) => format!("MissingHeaders: {}", e),
             PostParseError::NotAHeader(ref e) => format!("NotAHeader: {}", e),
+            PostParseError::BadFrontMatter(ref e) => format!("BadFrontMatter: {}", e),
+            PostParseError::InvalidHeader(ref k, ref e) => format!("InvalidHeader: {}: {}", k, e),
+            PostParseError::AtLine(line, ref e) => format!("line {}: {}", line, e),
         };
         write!(f, "{}", t)
     }
@@ -0,0 +1,163 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone};
+use std::fmt;
+
+/// The value of a header. Headers written in the simple `Key: value` format are always strings,
+/// while TOML and YAML front matter can give them a real type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    List(Vec<Value>),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    Date(DateTime<FixedOffset>),
+}
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Lists are returned item by item, and strings are split on commas so `Tags: a, b` works the
+    /// same as `tags = ["a", "b"]`
+    pub fn as_list(&self) -> Vec<String> {
+        match self {
+            Value::List(v) => v.iter().map(|i| i.to_string()).collect(),
+            Value::String(s) => s
+                .split(',')
+                .map(|i| i.trim().to_string())
+                .filter(|i| !i.is_empty())
+                .collect(),
+            v => vec![v.to_string()],
+        }
+    }
+
+    /// Booleans are either native booleans, or strings like `true`, `yes` or `off`
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            Value::String(s) => match s.trim().to_lowercase().as_str() {
+                "true" | "yes" | "on" => Some(true),
+                "false" | "no" | "off" => Some(false),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Integers are either native integers, or strings of digits
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Integer(i) => Some(*i),
+            Value::String(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Dates are either native dates, or strings in RFC 2822 or RFC 3339 format
+    pub fn as_date(&self) -> Option<DateTime<FixedOffset>> {
+        match self {
+            Value::Date(d) => Some(*d),
+            Value::String(s) => parse_date(s),
+            _ => None,
+        }
+    }
+}
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "{}", s),
+            Value::List(v) => write!(
+                f,
+                "{}",
+                v.iter()
+                    .map(|i| i.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Value::Integer(i) => write!(f, "{}", i),
+            Value::Float(n) => write!(f, "{}", n),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Date(d) => write!(f, "{}", d.to_rfc2822()),
+        }
+    }
+}
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+impl From<Vec<String>> for Value {
+    fn from(v: Vec<String>) -> Self {
+        Value::List(v.into_iter().map(Value::String).collect())
+    }
+}
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+impl From<DateTime<FixedOffset>> for Value {
+    fn from(d: DateTime<FixedOffset>) -> Self {
+        Value::Date(d)
+    }
+}
+impl PartialEq<&str> for Value {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == Some(other)
+    }
+}
+
+/// Parse an RFC 2822 date, an RFC 3339 date, or a bare `YYYY-MM-DD` date (taken as midnight UTC)
+pub fn parse_date(s: &str) -> Option<DateTime<FixedOffset>> {
+    let s = s.trim();
+    if let Ok(d) = DateTime::parse_from_rfc2822(s) {
+        return Some(d);
+    }
+    if let Ok(d) = DateTime::parse_from_rfc3339(s) {
+        return Some(d);
+    }
+    if let Ok(d) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Some(FixedOffset::east(0).from_utc_datetime(&d.and_hms(0, 0, 0)));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_date, Value};
+
+    #[test]
+    fn dates() {
+        let a = parse_date("Thu, 18 Oct 2018 10:00:00 +0000").unwrap();
+        let b = parse_date("2018-10-18T10:00:00Z").unwrap();
+        assert_eq!(a, b);
+        let c = parse_date("2018-10-18").unwrap();
+        assert_eq!(c.to_rfc3339(), "2018-10-18T00:00:00+00:00");
+        assert!(parse_date("yesterday").is_none());
+    }
+
+    #[test]
+    fn lists() {
+        let v = Value::String("a, b,,c ".to_string());
+        assert_eq!(v.as_list(), vec!["a", "b", "c"]);
+        let v = Value::List(vec![Value::String("a, b".to_string()), Value::Integer(1)]);
+        assert_eq!(v.as_list(), vec!["a, b", "1"]);
+    }
+
+    #[test]
+    fn display() {
+        let v = Value::List(vec![Value::String("a b".to_string()), Value::Integer(1)]);
+        assert_eq!(v.to_string(), "a b, 1");
+        let v = Value::Date(parse_date("2018-10-18T10:00:00Z").unwrap());
+        assert_eq!(v.to_string(), "Thu, 18 Oct 2018 10:00:00 +0000");
+        assert_eq!(v.as_date(), parse_date("2018-10-18T10:00:00Z"));
+    }
+}
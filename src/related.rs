@@ -0,0 +1,167 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::util::html::{tokenize, unescape, Token};
+
+/// Words too common to say anything about what a post is about
+const STOP_WORDS: &[&str] = &[
+    "about", "after", "again", "also", "and", "are", "because", "been", "before", "but", "can",
+    "could", "did", "does", "for", "from", "had", "has", "have", "her", "his", "how", "into",
+    "its", "just", "more", "most", "not", "now", "one", "only", "other", "our", "out", "over",
+    "she", "some", "than", "that", "the", "their", "them", "then", "there", "these", "they",
+    "this", "those", "too", "very", "was", "were", "what", "when", "where", "which", "while",
+    "who", "why", "will", "with", "would", "you", "your",
+];
+
+/// How much a shared tag counts for compared to the text similarity, which is at most 1
+const TAG_WEIGHT: f64 = 1.0;
+
+/// Words in the title count this many times over words in the body
+const TITLE_WEIGHT: usize = 3;
+
+/// What related posts are worked out from
+pub struct Doc {
+    pub tags: Vec<String>,
+    pub title: String,
+    /// The post's body. Any markup in it is ignored.
+    pub body: String,
+}
+
+/// The lowercased words in *s* worth comparing, leaving out tags if *s* is HTML
+fn terms(s: &str) -> Vec<String> {
+    let mut text = String::new();
+    for token in tokenize(s) {
+        match token {
+            Token::Text(t) => text += &unescape(t),
+            _ => text.push(' '),
+        }
+    }
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() >= 3 && !w.chars().all(|c| c.is_numeric()))
+        .map(|w| w.to_lowercase())
+        .filter(|w| !STOP_WORDS.contains(&w.as_str()))
+        .collect()
+}
+
+/// Term frequencies scaled by how rare each term is across all *docs*, normalized to unit length.
+/// BTreeMaps keep the sums in the same order every time, so scores are exactly the same between
+/// builds.
+fn weighted_terms(docs: &[Doc]) -> Vec<BTreeMap<String, f64>> {
+    let counts: Vec<BTreeMap<String, usize>> = docs
+        .iter()
+        .map(|d| {
+            let mut m = BTreeMap::new();
+            for t in terms(&d.title) {
+                *m.entry(t).or_insert(0) += TITLE_WEIGHT;
+            }
+            for t in terms(&d.body) {
+                *m.entry(t).or_insert(0) += 1;
+            }
+            m
+        })
+        .collect();
+    let mut doc_freq: BTreeMap<&str, usize> = BTreeMap::new();
+    for m in &counts {
+        for t in m.keys() {
+            *doc_freq.entry(t.as_str()).or_insert(0) += 1;
+        }
+    }
+    let n = docs.len() as f64;
+    counts
+        .iter()
+        .map(|m| {
+            let mut w: BTreeMap<String, f64> = m
+                .iter()
+                .map(|(t, c)| {
+                    (
+                        t.clone(),
+                        *c as f64 * (n / doc_freq[t.as_str()] as f64).ln(),
+                    )
+                })
+                .filter(|(_, w)| *w > 0.0)
+                .collect();
+            let len = w.values().map(|x| x * x).sum::<f64>().sqrt();
+            if len > 0.0 {
+                for x in w.values_mut() {
+                    *x /= len;
+                }
+            }
+            w
+        })
+        .collect()
+}
+
+fn cosine(a: &BTreeMap<String, f64>, b: &BTreeMap<String, f64>) -> f64 {
+    let (small, big) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    small
+        .iter()
+        .filter_map(|(t, x)| big.get(t).map(|y| x * y))
+        .sum()
+}
+
+/// For each of *docs*, the indexes of up to *count* other docs most like it, best first. Docs
+/// with nothing in common aren't related at all. Ties go to the doc that comes first in *docs*.
+pub fn related(docs: &[Doc], count: usize) -> Vec<Vec<usize>> {
+    if count == 0 {
+        return vec![vec![]; docs.len()];
+    }
+    let weights = weighted_terms(docs);
+    let tags: Vec<BTreeSet<String>> = docs
+        .iter()
+        .map(|d| d.tags.iter().map(|t| t.to_lowercase()).collect())
+        .collect();
+    (0..docs.len())
+        .map(|i| {
+            let mut scores: Vec<(f64, usize)> = (0..docs.len())
+                .filter(|j| *j != i)
+                .map(|j| {
+                    let shared = tags[i].intersection(&tags[j]).count() as f64;
+                    (shared * TAG_WEIGHT + cosine(&weights[i], &weights[j]), j)
+                })
+                .filter(|(score, _)| *score > 0.0)
+                .collect();
+            scores.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap().then(a.1.cmp(&b.1)));
+            scores.into_iter().take(count).map(|(_, j)| j).collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{related, terms, Doc};
+
+    fn doc(tags: &[&str], title: &str, body: &str) -> Doc {
+        Doc {
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            title: title.to_string(),
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn terms_skip_markup() {
+        assert_eq!(
+            terms("<p class='x'>The Rust <em>borrow</em> checker, 2019</p>"),
+            vec!["rust", "borrow", "checker"]
+        );
+    }
+
+    #[test]
+    fn ranking() {
+        let docs = vec![
+            doc(
+                &["rust"],
+                "Borrow checker tips",
+                "Lifetimes and the borrow checker.",
+            ),
+            doc(&[], "More on the borrow checker", "Lifetimes again."),
+            doc(&["Rust"], "Cargo workspaces", "Splitting crates up."),
+            doc(&[], "Sourdough", "Flour, water and salt."),
+        ];
+        let r = related(&docs, 2);
+        assert_eq!(r[0], vec![2, 1]);
+        assert_eq!(r[1], vec![0]);
+        assert_eq!(r[3], Vec::<usize>::new());
+        assert_eq!(related(&docs, 2), r);
+        assert_eq!(related(&docs, 0)[0], Vec::<usize>::new());
+    }
+}
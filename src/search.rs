@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+
+use crate::util::html::text_content;
+use crate::util::json::quote;
+use crate::words::snippet;
+
+/// How much more a word counts for in a title or a tag than in the body
+const TITLE_WEIGHT: usize = 5;
+const TAG_WEIGHT: usize = 3;
+
+/// How much of the start of each post is kept to show under its title in results
+const SNIPPET_LEN: usize = 160;
+
+/// A post as it goes in the search index
+pub struct SearchDoc {
+    pub title: String,
+    pub url: String,
+    /// Already formatted for showing
+    pub date: String,
+    pub tags: Vec<String>,
+    /// The rendered post
+    pub html: String,
+}
+
+/// The lowercased words in *s*
+fn terms(s: &str) -> impl Iterator<Item = String> + '_ {
+    s.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+}
+
+/// The search index for *docs* as JSON. It has a list of docs with what's shown for each result,
+/// and every word that appears in them with a flat list of the docs it's in and how much it counts
+/// for in each, like `"cat": [0, 6, 3, 1]`. Words are in sorted order so the index is the same
+/// between builds.
+pub fn index_json(docs: &[SearchDoc]) -> String {
+    let mut index: BTreeMap<String, BTreeMap<usize, usize>> = BTreeMap::new();
+    let mut entries = vec![];
+    for (i, doc) in docs.iter().enumerate() {
+        let text = text_content(&doc.html, &["script", "style"]);
+        let mut add = |term: String, weight: usize| {
+            *index.entry(term).or_default().entry(i).or_insert(0) += weight;
+        };
+        for t in terms(&doc.title) {
+            add(t, TITLE_WEIGHT);
+        }
+        for tag in &doc.tags {
+            for t in terms(tag) {
+                add(t, TAG_WEIGHT);
+            }
+        }
+        for t in terms(&text) {
+            add(t, 1);
+        }
+        let tags: Vec<String> = doc.tags.iter().map(|t| quote(t)).collect();
+        entries.push(format!(
+            "{{\"t\":{},\"u\":{},\"d\":{},\"g\":[{}],\"s\":{}}}",
+            quote(&doc.title),
+            quote(&doc.url),
+            quote(&doc.date),
+            tags.join(","),
+            quote(&snippet(&text, SNIPPET_LEN))
+        ));
+    }
+    let terms: Vec<String> = index
+        .iter()
+        .map(|(term, postings)| {
+            let flat: Vec<String> = postings
+                .iter()
+                .flat_map(|(doc, weight)| vec![doc.to_string(), weight.to_string()])
+                .collect();
+            format!("{}:[{}]", quote(term), flat.join(","))
+        })
+        .collect();
+    format!(
+        "{{\"docs\":[\n{}\n],\n\"terms\":{{\n{}\n}}}}\n",
+        entries.join(",\n"),
+        terms.join(",\n")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{index_json, SearchDoc};
+
+    #[test]
+    fn index() {
+        let docs = vec![
+            SearchDoc {
+                title: "Cats".to_string(),
+                url: "/posts/cats.html".to_string(),
+                date: "May 1, 2019".to_string(),
+                tags: vec!["Pets".to_string()],
+                html: "<p>Cats &amp; dogs</p><script>var x;</script>".to_string(),
+            },
+            SearchDoc {
+                title: "Dogs".to_string(),
+                url: "/posts/dogs.html".to_string(),
+                date: "May 2, 2019".to_string(),
+                tags: vec![],
+                html: "<p>Dogs, dogs, dogs.</p>".to_string(),
+            },
+        ];
+        assert_eq!(
+            index_json(&docs),
+            "{\"docs\":[
+{\"t\":\"Cats\",\"u\":\"/posts/cats.html\",\"d\":\"May 1, 2019\",\"g\":[\"Pets\"],\"s\":\"Cats & dogs\"},
+{\"t\":\"Dogs\",\"u\":\"/posts/dogs.html\",\"d\":\"May 2, 2019\",\"g\":[],\"s\":\"Dogs, dogs, dogs.\"}
+],
+\"terms\":{
+\"cats\":[0,6],
+\"dogs\":[0,1,1,8],
+\"pets\":[0,3]
+}}
+"
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+
+use crate::post::file::File as PostFile;
+use crate::util::slug::slugify;
+
+/// Posts that go together, like the parts of a tutorial
+#[derive(Debug, PartialEq)]
+pub struct Series {
+    /// As written in the `Series` header of its first part
+    pub name: String,
+    /// The series page is at `/series/<slug>/`, under the language's own directory if it isn't
+    /// the default language
+    pub slug: String,
+    pub lang: String,
+    /// Indexes of the posts in the series, in reading order
+    pub parts: Vec<usize>,
+}
+
+/// Group *posts* by their `Series` header. Posts are in the same series if they're in the same
+/// language, with posts without a `Lang` header in *default_lang*, and their series names have the
+/// same slug, so `Learning Rust` and `learning rust` are one series. Parts are ordered by
+/// `Series-Part` and then by date, with parts that don't give a number after those that do.
+/// Series are ordered by language and then slug.
+pub fn find_series<'a, I>(posts: I, default_lang: &str) -> Vec<Series>
+where
+    I: IntoIterator<Item = &'a PostFile>,
+{
+    let posts: Vec<&PostFile> = posts.into_iter().collect();
+    let mut by_slug: BTreeMap<(String, String), Vec<usize>> = BTreeMap::new();
+    for (i, pf) in posts.iter().enumerate() {
+        if let Some(name) = pf.series() {
+            let lang = pf.lang().unwrap_or_else(|| default_lang.to_string());
+            by_slug.entry((lang, slugify(&name))).or_default().push(i);
+        }
+    }
+    by_slug
+        .into_iter()
+        .map(|((lang, slug), mut parts)| {
+            parts.sort_by(|a, b| {
+                let (a, b) = (posts[*a], posts[*b]);
+                let part = |pf: &PostFile| pf.series_part().unwrap_or(u32::MAX);
+                part(a)
+                    .cmp(&part(b))
+                    .then_with(|| a.date().cmp(&b.date()))
+                    .then_with(|| a.id().cmp(&b.id()))
+            });
+            Series {
+                name: posts[parts[0]].series().unwrap(),
+                slug,
+                lang,
+                parts,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_series;
+    use crate::post::file::File as PostFile;
+    use std::io::BufReader;
+
+    fn post(id: &str, date: &str, series: &str) -> PostFile {
+        let text = format!(
+            "Title: T\nAuthor: A\nID: {}\nDate: {}\n{}\n\nBody",
+            id, date, series
+        );
+        let br = BufReader::new(std::io::Cursor::new(text.into_bytes()));
+        PostFile::new_from_buf(Box::new(br), None, None).unwrap()
+    }
+
+    #[test]
+    fn grouping() {
+        let posts = vec![
+            post("a", "2020-01-03", "Series: Learning Rust\nSeries-Part: 2"),
+            post("b", "2020-01-01", "Series: learning rust"),
+            post("c", "2020-01-02", "Series: Learning Rust\nSeries-Part: 1"),
+            post("d", "2020-01-02", ""),
+            post("e", "2020-01-05", "Series: Baking"),
+            post("f", "2020-01-05", "Series: Learning Rust\nLang: de"),
+        ];
+        let series = find_series(&posts, "en");
+        assert_eq!(series.len(), 3);
+        assert_eq!(series[0].lang, "de");
+        assert_eq!(series[0].parts, vec![5]);
+        let series = &series[1..];
+        assert_eq!(series[0].slug, "baking");
+        assert_eq!(series[1].name, "Learning Rust");
+        assert_eq!(series[1].slug, "learning-rust");
+        assert_eq!(series[1].parts, vec![2, 0, 1]);
+    }
+}
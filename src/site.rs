@@ -0,0 +1,249 @@
+use config::Config;
+
+use crate::util::html::rewrite_attrs;
+
+/// Attributes whose value is a single URL
+const URL_ATTRS: &[&str] = &["href", "src", "poster", "action", "data-index"];
+
+/// Where the blog is served from, and how pages link to each other there.
+///
+/// Pages are rendered with links relative to the root of the blog, like `/posts/a.html`, which
+/// are called site paths here. `rewrite` turns them into what the blog is really served at once
+/// a page is done.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SiteUrl {
+    /// Like `https://example.org`, or empty if the blog doesn't know what host it's on
+    origin: String,
+    /// Where the blog is on its host, like `/blog`, or empty for the root
+    path: String,
+    /// Link between pages with relative URLs, so they work wherever the pages are
+    relative: bool,
+}
+impl SiteUrl {
+    /// *base_url* is a full URL like `https://example.org/blog/`, just a path like `/blog/`, or
+    /// empty for the root of whatever host the blog is on
+    pub fn new(base_url: &str, relative: bool) -> Result<Self, String> {
+        let (origin, path) = if base_url.starts_with("http://") || base_url.starts_with("https://")
+        {
+            let host_start = base_url.find("//").unwrap() + 2;
+            match base_url[host_start..].find('/') {
+                Some(i) => base_url.split_at(host_start + i),
+                None => (base_url, ""),
+            }
+        } else if base_url.is_empty() || base_url.starts_with('/') {
+            ("", base_url)
+        } else {
+            return Err(format!(
+                "site.base_url: '{}' should start with http://, https:// or /",
+                base_url
+            ));
+        };
+        if path.contains(['?', '#']) {
+            return Err(format!(
+                "site.base_url: '{}' can't have a query or fragment",
+                base_url
+            ));
+        }
+        Ok(SiteUrl {
+            origin: origin.to_string(),
+            path: path.trim_end_matches('/').to_string(),
+            relative,
+        })
+    }
+
+    pub fn from_config(conf: &Config) -> Result<Self, String> {
+        SiteUrl::new(
+            &conf.get_str("site.base_url").unwrap(),
+            conf.get_bool("site.relative_links").unwrap(),
+        )
+    }
+
+    /// The full URL of the site path *path*, for feeds and anything else read away from the
+    /// blog. Without a host in `site.base_url` this is as close as it gets, starting at the host.
+    pub fn absolute(&self, path: &str) -> String {
+        format!("{}{}{}", self.origin, self.path, path)
+    }
+
+    /// The full URL of the site path *path* if `site.base_url` says what host the blog is on, for
+    /// places that only take full URLs like link previews
+    pub fn full_url(&self, path: &str) -> Option<String> {
+        if self.origin.is_empty() {
+            None
+        } else {
+            Some(self.absolute(path))
+        }
+    }
+
+    /// How the page at site path *page* links to site path *path*
+    pub fn link(&self, page: &str, path: &str) -> String {
+        if !self.relative {
+            return format!("{}{}", self.path, path);
+        }
+        let split = path.find(['?', '#']).unwrap_or(path.len());
+        let (path, suffix) = path.split_at(split);
+        // Opened from a disk, a directory shows a listing and not its index
+        let index = if path.ends_with('/') {
+            "index.html"
+        } else {
+            ""
+        };
+        let from: Vec<&str> = page.split('/').filter(|s| !s.is_empty()).collect();
+        let from = &from[..from
+            .len()
+            .saturating_sub(if page.ends_with('/') { 0 } else { 1 })];
+        let to: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let to_dirs = if path.ends_with('/') {
+            &to[..]
+        } else {
+            &to[..to.len().saturating_sub(1)]
+        };
+        let common = from.iter().zip(to_dirs).take_while(|(a, b)| a == b).count();
+        let mut parts = vec![".."; from.len() - common];
+        parts.extend(&to[common..]);
+        let mut link = parts.join("/");
+        if !index.is_empty() {
+            if !link.is_empty() {
+                link += "/";
+            }
+            link += index;
+        }
+        link + suffix
+    }
+
+    /// The site path of *url* if it's one of the blog's own, either a full URL under
+    /// `site.base_url` or a path from the root of the host under its path
+    pub fn site_path(&self, url: &str) -> Option<String> {
+        let rest = if !self.origin.is_empty() && url.starts_with(&self.origin) {
+            &url[self.origin.len()..]
+        } else if url.starts_with('/') && !url.starts_with("//") {
+            url
+        } else {
+            return None;
+        };
+        let rest = rest.strip_prefix(&self.path)?;
+        if rest.is_empty() {
+            Some("/".to_string())
+        } else if rest.starts_with(['/', '?', '#']) {
+            Some(rest.to_string())
+        } else {
+            None
+        }
+    }
+
+    /// Change each site path in a URL attribute of *html* with *f*
+    fn rewrite_with<F>(html: &str, f: F) -> String
+    where
+        F: Fn(&str) -> String,
+    {
+        let is_site_path = |url: &str| url.starts_with('/') && !url.starts_with("//");
+        rewrite_attrs(html, |_, attr, value| {
+            if URL_ATTRS.contains(&attr) && is_site_path(value) {
+                Some(f(value))
+            } else if attr == "srcset" && value.split(',').any(|c| is_site_path(c.trim())) {
+                let candidates: Vec<String> = value
+                    .split(',')
+                    .map(|c| {
+                        // Each candidate is a URL and then maybe a width or density
+                        let c = c.trim();
+                        let (url, descriptor) = c.split_at(c.find(' ').unwrap_or(c.len()));
+                        if is_site_path(url) {
+                            f(url) + descriptor
+                        } else {
+                            c.to_string()
+                        }
+                    })
+                    .collect();
+                Some(candidates.join(", "))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Make the links in *html*, the page at site path *page*, go where the blog is served
+    pub fn rewrite(&self, html: &str, page: &str) -> String {
+        if self.path.is_empty() && !self.relative {
+            return html.to_string();
+        }
+        Self::rewrite_with(html, |url| self.link(page, url))
+    }
+
+    /// Make the links in *html* full URLs, for feeds
+    pub fn rewrite_absolute(&self, html: &str) -> String {
+        if self.origin.is_empty() && self.path.is_empty() {
+            return html.to_string();
+        }
+        Self::rewrite_with(html, |url| self.absolute(url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SiteUrl;
+
+    #[test]
+    fn links() {
+        let site = SiteUrl::new("https://example.org/blog/", false).unwrap();
+        assert_eq!(
+            site.link("/posts/a.html", "/static/style.css"),
+            "/blog/static/style.css"
+        );
+        assert_eq!(
+            site.absolute("/posts/a.html"),
+            "https://example.org/blog/posts/a.html"
+        );
+        assert_eq!(
+            site.site_path("https://example.org/blog/posts/a.html#x"),
+            Some("/posts/a.html#x".to_string())
+        );
+        assert_eq!(site.site_path("/blog"), Some("/".to_string()));
+        assert_eq!(
+            site.full_url("/"),
+            Some("https://example.org/blog/".to_string())
+        );
+        assert_eq!(site.site_path("/blogroll.html"), None);
+        assert_eq!(site.site_path("https://example.com/blog/"), None);
+        assert!(SiteUrl::new("example.org", false).is_err());
+
+        let site = SiteUrl::new("", true).unwrap();
+        assert_eq!(
+            site.link("/posts/a.html", "/static/style.css"),
+            "../static/style.css"
+        );
+        assert_eq!(site.link("/posts/a.html", "/posts/b.html#c"), "b.html#c");
+        assert_eq!(site.link("/index.html", "/"), "index.html");
+        assert_eq!(
+            site.link("/de/index.html", "/de/series/x/"),
+            "series/x/index.html"
+        );
+        assert_eq!(
+            site.link("/de/series/x/index.html", "/"),
+            "../../../index.html"
+        );
+        assert_eq!(site.absolute("/feed.xml"), "/feed.xml");
+        assert_eq!(site.full_url("/feed.xml"), None);
+    }
+
+    #[test]
+    fn rewrite() {
+        let html = "<a href='/posts/a.html'>a</a><a href='#top'>top</a>\
+                    <img src='/static/a.png' srcset='/static/a-10w.png 10w, https://x.org/b.png 20w' />";
+        assert_eq!(SiteUrl::default().rewrite(html, "/index.html"), html);
+        assert_eq!(
+            SiteUrl::new("/blog", false).unwrap().rewrite(html, "/index.html"),
+            "<a href=\"/blog/posts/a.html\">a</a><a href='#top'>top</a>\
+             <img src=\"/blog/static/a.png\" srcset=\"/blog/static/a-10w.png 10w, https://x.org/b.png 20w\" />"
+        );
+        assert_eq!(
+            SiteUrl::new("", true).unwrap().rewrite(html, "/posts/b.html"),
+            "<a href=\"a.html\">a</a><a href='#top'>top</a>\
+             <img src=\"../static/a.png\" srcset=\"../static/a-10w.png 10w, https://x.org/b.png 20w\" />"
+        );
+        assert_eq!(
+            SiteUrl::new("https://example.org", false)
+                .unwrap()
+                .rewrite_absolute("<a href='/posts/a.html'>a</a>"),
+            "<a href=\"https://example.org/posts/a.html\">a</a>"
+        );
+    }
+}
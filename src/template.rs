@@ -1,20 +1,139 @@
-pub fn begin_html(title: &str) -> String {
+use chrono::{DateTime, FixedOffset};
+
+use crate::authors::Author;
+use crate::i18n::{fill, Strings};
+use crate::menu::MenuItem;
+use crate::toc::Heading;
+use crate::util::html::escape;
+
+/// The start of every page. *head_links* is extra `<link>` tags, like feeds and translations.
+pub fn begin_html(
+    title: &str,
+    strings: &Strings,
+    css_hrefs: &[String],
+    favicon_href: &str,
+    head_links: &str,
+) -> String {
+    let css_links: Vec<String> = css_hrefs
+        .iter()
+        .map(|href| {
+            format!(
+                "    <link href='{}' rel='stylesheet' type='text/css' />",
+                href
+            )
+        })
+        .collect();
     format!(
         "
 <!DOCTYPE html>
-<html>
+<html lang='{lang}' dir='{dir}'>
 <head>
-    <title>{title}</title>
-    <link href='/static/style.css' rel='stylesheet' type='text/css' />
-    <link rel='icon' type='image/png' href='/static/img/favicon.png' />
     <meta charset='utf-8' />
-</head>
+    <title>{title}</title>
+{css_links}
+    <link rel='icon' type='image/png' href='{favicon_href}' />
+{head_links}</head>
 <body>
 <div id='page_content'>\n",
-        title = title
+        lang = strings.lang,
+        dir = strings.dir,
+        title = title,
+        css_links = css_links.join("\n"),
+        favicon_href = favicon_href,
+        head_links = head_links
     )
 }
 
+/// A `<link>` to the Atom feed at *href*
+pub fn feed_link(title: &str, href: &str) -> String {
+    format!(
+        "    <link rel='alternate' type='application/atom+xml' title='{title}' href='{href}' />\n",
+        title = escape(title),
+        href = escape(href)
+    )
+}
+
+/// `<link>`s to the same page in other languages, as pairs of language code and URL
+pub fn hreflang_links(alternates: &[(String, String)]) -> String {
+    let mut s = String::new();
+    for (lang, href) in alternates {
+        s += &format!(
+            "    <link rel='alternate' hreflang='{lang}' href='{href}' />\n",
+            lang = escape(lang),
+            href = escape(href)
+        );
+    }
+    s
+}
+
+/// What a page says about itself to search engines and to sites that show previews of links
+pub struct PageMeta<'a> {
+    pub title: &'a str,
+    pub description: &'a str,
+    /// `website`, or `article` for a post
+    pub kind: &'a str,
+    pub site_name: &'a str,
+    /// The full URL of the page, if the blog knows what host it's on
+    pub url: Option<&'a str>,
+    /// The full URL of the image shown with the page
+    pub image: Option<&'a str>,
+    /// When a post was published and last changed
+    pub published: Option<&'a DateTime<FixedOffset>>,
+    pub modified: Option<&'a DateTime<FixedOffset>>,
+}
+
+/// `<meta>` tags with the description of a page, OpenGraph and Twitter card tags, and a
+/// canonical `<link>` if the page's URL is known
+pub fn meta_tags(m: &PageMeta) -> String {
+    let mut s = String::new();
+    let mut meta = |attr: &str, name: &str, content: &str| {
+        s += &format!(
+            "    <meta {}='{}' content='{}' />\n",
+            attr,
+            name,
+            escape(content)
+        );
+    };
+    if !m.description.is_empty() {
+        meta("name", "description", m.description);
+    }
+    meta("property", "og:type", m.kind);
+    meta("property", "og:title", m.title);
+    if !m.description.is_empty() {
+        meta("property", "og:description", m.description);
+    }
+    meta("property", "og:site_name", m.site_name);
+    if let Some(url) = m.url {
+        meta("property", "og:url", url);
+    }
+    if let Some(image) = m.image {
+        meta("property", "og:image", image);
+    }
+    if let Some(date) = m.published {
+        meta("property", "article:published_time", &date.to_rfc3339());
+    }
+    if let Some(date) = m.modified {
+        meta("property", "article:modified_time", &date.to_rfc3339());
+    }
+    let card = if m.image.is_some() {
+        "summary_large_image"
+    } else {
+        "summary"
+    };
+    meta("name", "twitter:card", card);
+    meta("name", "twitter:title", m.title);
+    if !m.description.is_empty() {
+        meta("name", "twitter:description", m.description);
+    }
+    if let Some(image) = m.image {
+        meta("name", "twitter:image", image);
+    }
+    if let Some(url) = m.url {
+        s += &format!("    <link rel='canonical' href='{}' />\n", escape(url));
+    }
+    s
+}
+
 pub fn end_html() -> String {
     "
 </div> <!-- page_content -->
@@ -23,48 +142,532 @@ pub fn end_html() -> String {
         .to_string()
 }
 
-pub fn page_header(title: &str, subtitle: &str) -> String {
+/// The top of every page, the one at site path *page*. *menu* is the links in the site's
+/// navigation, if it has any, and the one to *page* is marked as the current one.
+pub fn page_header(
+    title: &str,
+    subtitle: &str,
+    img_src: &str,
+    menu: &[MenuItem],
+    page: &str,
+) -> String {
+    let mut nav_html = String::new();
+    if !menu.is_empty() {
+        nav_html += "    <nav id='site_nav'>\n    <ul>\n";
+        for item in menu {
+            let (class, current) = if item.is_current(page) {
+                (" class='current'", " aria-current='page'")
+            } else {
+                ("", "")
+            };
+            nav_html += &format!(
+                "        <li{class}><a href='{url}'{current}>{label}</a></li>\n",
+                class = class,
+                url = escape(&item.url),
+                current = current,
+                label = escape(&item.label)
+            );
+        }
+        nav_html += "    </ul>\n    </nav>\n";
+    }
     format!(
         "
 <header>
     <h1 id='blog_title'>{title}</h1>
     <h2 id='blog_subtitle'>{subtitle}</h2>
-    <img id='blog_img' src='/static/img/header.jpg' />
-</header>\n",
+    <img id='blog_img' src='{img_src}' />
+{nav_html}</header>\n",
         title = title,
-        subtitle = subtitle
+        subtitle = subtitle,
+        img_src = img_src,
+        nav_html = nav_html
     )
 }
 
-pub fn page_footer() -> String {
-    "
-<footer>
-</footer>\n"
-        .to_string()
+/// What goes at the bottom of every page
+pub struct PageFooter<'a> {
+    /// Like `© 2019–2026 John Doe`
+    pub copyright: Option<&'a str>,
+    /// The name of the license posts are under, and where to read it
+    pub license: &'a str,
+    pub license_url: &'a str,
+    /// The feed in the language of the page
+    pub feed_href: Option<&'a str>,
+    /// Anything else, as HTML
+    pub text: &'a str,
+}
+
+pub fn page_footer(f: &PageFooter, strings: &Strings) -> String {
+    let mut s = String::new();
+    if let Some(copyright) = f.copyright {
+        s += &format!("    <p class='copyright'>{}</p>\n", escape(copyright));
+    }
+    if !f.license.is_empty() {
+        let license = if f.license_url.is_empty() {
+            escape(f.license)
+        } else {
+            format!(
+                "<a rel='license' href='{}'>{}</a>",
+                escape(f.license_url),
+                escape(f.license)
+            )
+        };
+        s += &format!("    <p class='license'>{}</p>\n", license);
+    }
+    if !f.text.is_empty() {
+        s += &format!("    <div class='footer_text'>{}</div>\n", f.text);
+    }
+    if let Some(href) = f.feed_href {
+        s += &format!(
+            "    <ul class='footer_links'>\n        \
+             <li><a href='{}' type='application/atom+xml'>{}</a></li>\n    </ul>\n",
+            href, strings.feed
+        );
+    }
+    format!("\n<footer>\n{}</footer>\n", s)
+}
+
+/// Everything shown above a post's body
+pub struct PostHeader<'a> {
+    pub title: String,
+    /// Where the title links to, if anywhere
+    pub link: Option<String>,
+    /// Names with a link to their page, if they have one
+    pub authors: &'a [(String, Option<String>)],
+    pub date: &'a DateTime<FixedOffset>,
+    pub modified: Option<&'a DateTime<FixedOffset>>,
+    pub tags: &'a [String],
+    /// The number of words and minutes to read them, if shown
+    pub reading: Option<(usize, usize)>,
+    /// The post in other languages, as the name of the language, its code and the URL
+    pub translations: &'a [(String, String, String)],
 }
 
-pub fn post_header(title: String, author: String, link: Option<String>) -> String {
+pub fn post_header(h: &PostHeader, strings: &Strings) -> String {
     let mut s = String::new();
     s += "<div class='post_header'>\n";
-    s += &if link.is_some() {
-        format!(
+    s += &match h.link {
+        Some(ref link) => format!(
             "<h1 class='post_title'><a href='{link}'>{title}</a></h1>\n",
-            link = link.unwrap(),
-            title = title
-        )
-    } else {
-        format!("<h1 class='post_title'>{title}</h1>\n", title = title)
+            link = link,
+            title = h.title
+        ),
+        None => format!("<h1 class='post_title'>{title}</h1>\n", title = h.title),
     };
-    s += &format!("<p class='post_author'>{author}</p>\n", author = author);
-    // post date
-    // post mod date
+    let authors: Vec<String> = h
+        .authors
+        .iter()
+        .map(|(name, link)| match link {
+            Some(link) => format!("<a href='{link}'>{name}</a>", link = link, name = name),
+            None => name.clone(),
+        })
+        .collect();
+    let authors = match authors.split_last() {
+        Some((last, rest)) if !rest.is_empty() => rest.join(", ") + " " + &strings.and + " " + last,
+        _ => authors.join(""),
+    };
+    s += &format!("<p class='post_author'>{authors}</p>\n", authors = authors);
+    s += &format!(
+        "<p class='post_date'><time datetime='{iso}'>{date}</time>",
+        iso = h.date.to_rfc3339(),
+        date = strings.format_date(h.date)
+    );
+    if let Some(modified) = h.modified {
+        s += &format!(
+            ", {updated} <time datetime='{iso}'>{date}</time>",
+            updated = strings.updated,
+            iso = modified.to_rfc3339(),
+            date = strings.format_date(modified)
+        );
+    }
+    s += "</p>\n";
+    if let Some((words, minutes)) = h.reading {
+        let words_tmpl = if words == 1 {
+            &strings.word
+        } else {
+            &strings.words
+        };
+        s += &format!(
+            "<p class='post_reading'>{min_read} <span class='post_words'>({words})</span></p>\n",
+            min_read = fill(&strings.min_read, &[("n", minutes)]),
+            words = fill(words_tmpl, &[("n", words)])
+        );
+    }
+    if !h.tags.is_empty() {
+        s += &format!(
+            "<ul class='post_tags' aria-label='{label}'>\n",
+            label = strings.tags
+        );
+        for tag in h.tags {
+            s += &format!("    <li>{tag}</li>\n", tag = tag);
+        }
+        s += "</ul>\n";
+    }
+    if !h.translations.is_empty() {
+        let links: Vec<String> = h
+            .translations
+            .iter()
+            .map(|(name, lang, link)| {
+                format!(
+                    "<a href='{link}' hreflang='{lang}' lang='{lang}'>{name}</a>",
+                    link = link,
+                    lang = lang,
+                    name = name
+                )
+            })
+            .collect();
+        s += &format!(
+            "<p class='post_translations'>{label}: {links}</p>\n",
+            label = strings.translations,
+            links = links.join(", ")
+        );
+    }
     // post permalink
     s += "</div> <!-- post_header -->\n";
     s
 }
 
-pub fn post_footer() -> String {
-    String::new()
+/// A nested list of links to *headings*
+pub fn toc(headings: &[Heading]) -> String {
+    let base = headings.iter().map(|h| h.level).min().unwrap_or(0);
+    let mut s = String::new();
+    s += "<nav class='toc'>\n<ul>\n";
+    let mut depth = 0;
+    for (idx, h) in headings.iter().enumerate() {
+        let d = h.level - base;
+        if idx > 0 && d <= depth {
+            s += "</li>\n";
+        }
+        while depth < d {
+            s += "<ul>\n";
+            depth += 1;
+        }
+        while depth > d {
+            s += "</ul>\n</li>\n";
+            depth -= 1;
+        }
+        s += &format!("<li><a href='#{id}'>{text}</a>\n", id = h.id, text = h.text);
+    }
+    if !headings.is_empty() {
+        s += "</li>\n";
+    }
+    while depth > 0 {
+        s += "</ul>\n</li>\n";
+        depth -= 1;
+    }
+    s += "</ul>\n</nav> <!-- toc -->\n";
+    s
+}
+
+/// Where a post's table of contents goes relative to the post itself. *toc* is empty if the post
+/// doesn't get one. Change this, and the .post_layout CSS, to move it into a sidebar.
+pub fn post_layout(article: &str, toc: &str) -> String {
+    if toc.is_empty() {
+        return article.to_string();
+    }
+    format!(
+        "<div class='post_layout'>\n{toc}{article}</div> <!-- post_layout -->\n",
+        toc = toc,
+        article = article
+    )
+}
+
+/// A link to another post, as its title and URL
+pub type PostLink = (String, String);
+
+/// The page about an author, with everything they've written
+pub fn author_index(
+    author: &Author,
+    avatar_src: Option<&str>,
+    posts: &[PostLink],
+    strings: &Strings,
+) -> String {
+    let mut s = String::new();
+    s += "<article class='author_index'>\n";
+    if let Some(src) = avatar_src {
+        s += &format!(
+            "<img class='author_avatar' src='{src}' alt='{name}' />\n",
+            src = escape(src),
+            name = escape(&author.name)
+        );
+    }
+    s += &format!("<h1 class='post_title'>{name}</h1>\n", name = author.name);
+    if !author.bio.is_empty() {
+        s += &format!("<p class='author_bio'>{bio}</p>\n", bio = author.bio);
+    }
+    if !author.links.is_empty() {
+        s += "<ul class='author_links'>\n";
+        for (name, url) in &author.links {
+            s += &format!(
+                "    <li><a href='{url}'>{name}</a></li>\n",
+                url = escape(url),
+                name = name
+            );
+        }
+        s += "</ul>\n";
+    }
+    s += &format!(
+        "<h2>{posts}</h2>\n<ul class='author_posts'>\n",
+        posts = strings.posts
+    );
+    for (title, link) in posts {
+        s += &format!(
+            "    <li><a href='{link}'>{title}</a></li>\n",
+            link = escape(link),
+            title = title
+        );
+    }
+    s += "</ul>\n</article>\n";
+    s
+}
+
+/// The box on each part of a series saying which part it is and linking to the others. *current*
+/// is the index of this post in *parts*.
+pub fn series_box(
+    name: &str,
+    link: &str,
+    current: usize,
+    parts: &[PostLink],
+    strings: &Strings,
+) -> String {
+    let mut s = String::new();
+    s += "<aside class='series_box'>\n";
+    s += &format!(
+        "<p>{part} <a href='{link}'>{name}</a></p>\n<ol>\n",
+        part = fill(
+            &strings.series_part,
+            &[("n", current + 1), ("total", parts.len())]
+        ),
+        link = link,
+        name = name
+    );
+    for (idx, (title, link)) in parts.iter().enumerate() {
+        if idx == current {
+            s += &format!("    <li class='current'>{title}</li>\n", title = title);
+        } else {
+            s += &format!(
+                "    <li><a href='{link}'>{title}</a></li>\n",
+                link = link,
+                title = title
+            );
+        }
+    }
+    s += "</ol>\n</aside> <!-- series_box -->\n";
+    s
+}
+
+/// The landing page for a series, listing its *parts* in order
+pub fn series_index(name: &str, parts: &[PostLink], strings: &Strings) -> String {
+    let mut s = String::new();
+    s += "<article class='series_index'>\n";
+    s += &format!("<h1 class='post_title'>{name}</h1>\n", name = name);
+    s += &format!(
+        "<p class='series_count'>{count}</p>\n<ol>\n",
+        count = fill(&strings.series_count, &[("n", parts.len())])
+    );
+    for (title, link) in parts {
+        s += &format!(
+            "    <li><a href='{link}'>{title}</a></li>\n",
+            link = link,
+            title = title
+        );
+    }
+    s += "</ol>\n</article>\n";
+    s
+}
+
+/// A page that isn't a post, like an About page, with just its title and body
+pub fn standalone_page(title: &str, body_html: &str, strings: &Strings) -> String {
+    format!(
+        "<article class='page' lang='{lang}' dir='{dir}'>
+<h1 class='post_title'>{title}</h1>
+<div class='post_body'>
+{body_html}
+</div> <!-- post_body -->
+</article>\n",
+        lang = strings.lang,
+        dir = strings.dir,
+        title = title,
+        body_html = body_html
+    )
+}
+
+/// Links to the posts before and after this one, and to posts like it
+pub fn post_footer(
+    prev: Option<&PostLink>,
+    next: Option<&PostLink>,
+    related: &[PostLink],
+    strings: &Strings,
+) -> String {
+    let mut s = String::new();
+    if prev.is_none() && next.is_none() && related.is_empty() {
+        return s;
+    }
+    s += "<div class='post_footer'>\n";
+    if !related.is_empty() {
+        s += &format!(
+            "<div class='post_related'>\n<h2>{related}</h2>\n<ul>\n",
+            related = strings.related_posts
+        );
+        for (title, link) in related {
+            s += &format!(
+                "    <li><a href='{link}'>{title}</a></li>\n",
+                link = link,
+                title = title
+            );
+        }
+        s += "</ul>\n</div> <!-- post_related -->\n";
+    }
+    if prev.is_some() || next.is_some() {
+        s += "<nav class='post_nav'>\n";
+        if let Some((title, link)) = prev {
+            s += &format!(
+                "    <a class='post_prev' rel='prev' href='{link}'>&larr; {title}</a>\n",
+                link = link,
+                title = title
+            );
+        }
+        if let Some((title, link)) = next {
+            s += &format!(
+                "    <a class='post_next' rel='next' href='{link}'>{title} &rarr;</a>\n",
+                link = link,
+                title = title
+            );
+        }
+        s += "</nav> <!-- post_nav -->\n";
+    }
+    s += "</div> <!-- post_footer -->\n";
+    s
+}
+
+/// The search page. The script finds the index at *index_url* and shows results in the list.
+pub fn search_page(strings: &Strings, index_url: &str, script_src: &str) -> String {
+    format!(
+        "<article class='search'>
+<h1 class='post_title'>{search}</h1>
+<form role='search' onsubmit='return false'>
+    <input id='search_input' type='search' aria-label='{search}' autofocus />
+</form>
+<p id='search_none' hidden>{no_results}</p>
+<ol id='search_results' data-index='{index_url}'></ol>
+<noscript>{needs_javascript}</noscript>
+</article>
+<script src='{script_src}'></script>\n",
+        search = strings.search,
+        no_results = strings.no_results,
+        needs_javascript = strings.needs_javascript,
+        index_url = index_url,
+        script_src = script_src
+    )
+}
+
+/// Searches the index written by `search::index_json` as the reader types. Each word typed has to
+/// match a word in a post exactly, as the start of a word, or with a typo or two in longer words.
+pub fn search_js() -> String {
+    r#"(function () {
+    'use strict';
+    var input = document.getElementById('search_input');
+    var list = document.getElementById('search_results');
+    var none = document.getElementById('search_none');
+    var index = null;
+
+    function words(s) {
+        return s.toLowerCase().split(/[^\p{L}\p{N}]+/u).filter(function (w) { return w; });
+    }
+
+    // Edit distance between a and b, giving up once it's more than max
+    function distance(a, b, max) {
+        if (Math.abs(a.length - b.length) > max) { return max + 1; }
+        var prev = [], cur, i, j;
+        for (j = 0; j <= b.length; j++) { prev.push(j); }
+        for (i = 1; i <= a.length; i++) {
+            cur = [i];
+            var best = i;
+            for (j = 1; j <= b.length; j++) {
+                var cost = a[i - 1] === b[j - 1] ? 0 : 1;
+                cur.push(Math.min(prev[j] + 1, cur[j - 1] + 1, prev[j - 1] + cost));
+                best = Math.min(best, cur[j]);
+            }
+            if (best > max) { return max + 1; }
+            prev = cur;
+        }
+        return prev[b.length];
+    }
+
+    // Scores of the docs that match the query word q
+    function match(q) {
+        var scores = {};
+        var max = q.length >= 7 ? 2 : (q.length >= 4 ? 1 : 0);
+        Object.keys(index.terms).forEach(function (term) {
+            var factor = 0;
+            if (term === q) {
+                factor = 3;
+            } else if (term.lastIndexOf(q, 0) === 0) {
+                factor = 2;
+            } else if (max > 0 && distance(q, term, max) <= max) {
+                factor = 1;
+            }
+            if (!factor) { return; }
+            var p = index.terms[term];
+            for (var i = 0; i < p.length; i += 2) {
+                scores[p[i]] = (scores[p[i]] || 0) + p[i + 1] * factor;
+            }
+        });
+        return scores;
+    }
+
+    function search() {
+        var qs = words(input.value);
+        list.innerHTML = '';
+        none.hidden = true;
+        if (!index || !qs.length) { return; }
+        var total = null;
+        qs.forEach(function (q) {
+            var scores = match(q);
+            if (total === null) {
+                total = scores;
+                return;
+            }
+            // Every word has to match
+            Object.keys(total).forEach(function (doc) {
+                if (scores[doc]) { total[doc] += scores[doc]; } else { delete total[doc]; }
+            });
+        });
+        var docs = Object.keys(total).sort(function (a, b) {
+            return total[b] - total[a] || a - b;
+        });
+        none.hidden = docs.length > 0;
+        docs.slice(0, 50).forEach(function (i) {
+            var doc = index.docs[i];
+            var li = document.createElement('li');
+            var a = document.createElement('a');
+            a.href = doc.u;
+            a.textContent = doc.t;
+            var date = document.createElement('span');
+            date.className = 'post_date';
+            date.textContent = ' ' + doc.d;
+            var p = document.createElement('p');
+            p.textContent = doc.s;
+            li.appendChild(a);
+            li.appendChild(date);
+            li.appendChild(p);
+            list.appendChild(li);
+        });
+    }
+
+    var req = new XMLHttpRequest();
+    req.open('GET', list.getAttribute('data-index'));
+    req.onload = function () {
+        index = JSON.parse(req.responseText);
+        var q = new URLSearchParams(location.search).get('q');
+        if (q) { input.value = q; }
+        search();
+    };
+    req.send();
+    input.addEventListener('input', search);
+})();
+"#
+    .to_string()
 }
 
 pub fn css() -> String {
@@ -77,19 +680,21 @@ body {
 }
 header,
 footer,
-article {
+article,
+.toc {
     background-color: #FFF;
     border: 1px solid #CCC;
 }
 header {
     display: grid;
     grid-template-columns: auto 150px;
-    grid-template-rows: 1fr auto auto 6fr;
+    grid-template-rows: 1fr auto auto 6fr auto;
     grid-template-areas:
         '.        img'
         'title    img'
         'subtitle img'
-        '.        img';
+        '.        img'
+        'nav      nav';
     justify-items: center;
 }
 article {
@@ -120,9 +725,179 @@ a:hover {
     grid-area: img;
     align-self: center;
 }
+#site_nav {
+    grid-area: nav;
+    justify-self: stretch;
+    border-block-start: 1px solid #CCC;
+}
+#site_nav ul {
+    list-style: none;
+    margin: 0;
+    padding: 8px;
+    text-align: center;
+}
+#site_nav li {
+    display: inline;
+    margin-inline: 10px;
+}
+#site_nav .current a {
+    color: inherit;
+    font-weight: bold;
+}
+footer {
+    padding: 8px 40px;
+    text-align: center;
+    font-size: small;
+    color: #666;
+}
+footer p {
+    margin: 4px 0;
+}
+.footer_links {
+    list-style: none;
+    margin: 4px 0;
+    padding: 0;
+}
 img {
     max-width: 100%;
 }
+pre {
+    direction: ltr;
+    text-align: left;
+}
+.post_date,
+.post_reading,
+.post_translations {
+    color: #666;
+    font-size: small;
+}
+.post_tags {
+    list-style: none;
+    padding: 0;
+}
+.post_tags li {
+    display: inline;
+    margin-inline-end: 0.5em;
+    font-size: small;
+}
+.post_tags li::before {
+    content: '#';
+}
+.author_avatar {
+    float: inline-end;
+    max-width: 120px;
+    border-radius: 50%;
+}
+.author_links {
+    list-style: none;
+    padding: 0;
+}
+.author_links li {
+    display: inline;
+    margin-inline-end: 1em;
+}
+.series_box {
+    background-color: #F3F3F3;
+    border: 1px solid #CCC;
+    padding: 0 20px;
+    font-size: small;
+}
+.series_box .current {
+    font-weight: bold;
+}
+.post_footer {
+    border-top: 1px solid #CCC;
+    margin-top: 20px;
+}
+.post_related h2 {
+    font-size: medium;
+}
+.post_nav {
+    display: flex;
+    justify-content: space-between;
+    margin-top: 10px;
+}
+.post_next {
+    margin-inline-start: auto;
+}
+#search_input {
+    width: 100%;
+    font-size: large;
+}
+#search_results p {
+    margin-top: 0;
+    font-size: small;
+}
+.toc {
+    font-size: small;
+    padding: 10px 40px;
+}
+.toc ul {
+    padding-inline-start: 1.5em;
+}
 "
     .to_string()
 }
+
+/// Colors for the classes `highlight::highlight_code_blocks` puts on code, in the named *theme*
+pub fn highlight_css(theme: &str) -> Result<String, String> {
+    // background, text, comment, keyword, string, number and literal, type, function, attribute
+    // and macro, variable, highlighted line, line number
+    let colors = match theme {
+        "light" => [
+            "#F6F8FA", "#24292E", "#6A737D", "#D73A49", "#032F62", "#005CC5", "#6F42C1", "#6F42C1",
+            "#E36209", "#E36209", "#FFFBDD", "#959DA5",
+        ],
+        "dark" => [
+            "#282C34", "#ABB2BF", "#7F848E", "#C678DD", "#98C379", "#D19A66", "#E5C07B", "#61AFEF",
+            "#56B6C2", "#E06C75", "#3A3F4B", "#636D83",
+        ],
+        _ => return Err(format!("Unknown highlight theme '{}'", theme)),
+    };
+    Ok(format!(
+        "
+pre.highlight {{
+    background-color: {bg};
+    color: {fg};
+    padding: 0.5em 0;
+    overflow-x: auto;
+}}
+pre.highlight code {{
+    display: block;
+    padding: 0 1em;
+}}
+.hl-c {{ color: {comment}; font-style: italic; }}
+.hl-k {{ color: {keyword}; }}
+.hl-s {{ color: {string}; }}
+.hl-n, .hl-b {{ color: {number}; }}
+.hl-t {{ color: {ty}; }}
+.hl-f {{ color: {func}; }}
+.hl-a, .hl-m {{ color: {attr}; }}
+.hl-v {{ color: {var}; }}
+pre.highlight .line {{
+    display: inline-block;
+    width: 100%;
+}}
+pre.highlight .line.hl {{
+    background-color: {hl};
+}}
+pre.highlight .ln {{
+    color: {ln};
+    margin-right: 1em;
+    user-select: none;
+}}
+",
+        bg = colors[0],
+        fg = colors[1],
+        comment = colors[2],
+        keyword = colors[3],
+        string = colors[4],
+        number = colors[5],
+        ty = colors[6],
+        func = colors[7],
+        attr = colors[8],
+        var = colors[9],
+        hl = colors[10],
+        ln = colors[11],
+    ))
+}
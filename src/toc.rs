@@ -0,0 +1,173 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::util::html::{rewrite_tags, tokenize, unescape, Token};
+use crate::util::slug::slugify;
+
+/// Headings at these levels go in the table of contents
+const TOC_LEVELS: &[&str] = &["h2", "h3", "h4"];
+
+/// A heading in a post
+#[derive(Debug, PartialEq)]
+pub struct Heading {
+    /// 2 for `<h2>`, and so on
+    pub level: usize,
+    pub id: String,
+    /// The text of the heading, still escaped, without any tags that were in it
+    pub text: String,
+}
+
+/// Give every `<h2>` to `<h4>` in *html* an `id`, keeping any it already has, and return the new
+/// HTML with the headings in order. Ids come from the heading text, so they stay the same between
+/// builds as long as the text does, and get a number on the end when they'd clash.
+pub fn add_heading_ids(html: &str) -> (String, Vec<Heading>) {
+    // Find the headings and their text first, along with which opening tag each one starts at.
+    // A heading that's never closed, or has another one opened inside it, is left out.
+    let mut headings = vec![];
+    let mut current: Option<(usize, usize, Option<String>, String)> = None;
+    let mut opened = 0;
+    for token in tokenize(html) {
+        match token {
+            Token::Tag(tag, _) if TOC_LEVELS.contains(&tag.name.as_str()) => {
+                if tag.closing {
+                    if let Some(h) = current.take() {
+                        headings.push(h);
+                    }
+                } else {
+                    let level = tag.name[1..].parse().unwrap();
+                    let id = tag.attr("id").map(String::from);
+                    current = Some((opened, level, id, String::new()));
+                    opened += 1;
+                }
+            }
+            Token::Text(text) => {
+                if let Some((_, _, _, ref mut s)) = current {
+                    *s += text;
+                }
+            }
+            _ => {}
+        }
+    }
+    // Ids that are already there are kept as is, so they're taken before making new ones
+    let mut used: HashSet<String> = headings.iter().filter_map(|h| h.2.clone()).collect();
+    let mut ids_at = HashMap::new();
+    let headings: Vec<Heading> = headings
+        .into_iter()
+        .map(|(at, level, id, text)| {
+            let text = text.trim().to_string();
+            let id = id.unwrap_or_else(|| {
+                let mut base = slugify(&unescape(&text));
+                if base.is_empty() {
+                    base = "section".to_string();
+                }
+                let mut id = base.clone();
+                let mut n = 2;
+                while used.contains(&id) {
+                    id = format!("{}-{}", base, n);
+                    n += 1;
+                }
+                used.insert(id.clone());
+                id
+            });
+            ids_at.insert(at, id.clone());
+            Heading { level, id, text }
+        })
+        .collect();
+    let mut opened = 0;
+    let html = rewrite_tags(html, |tag| {
+        if tag.closing || !TOC_LEVELS.contains(&tag.name.as_str()) {
+            return false;
+        }
+        opened += 1;
+        match ids_at.get(&(opened - 1)) {
+            Some(id) if !tag.has_attr("id") => {
+                tag.set_attr("id", id);
+                true
+            }
+            _ => false,
+        }
+    });
+    (html, headings)
+}
+
+/// *html* with *prefix* put in front of every `id`, and of every `#fragment` link to one of them,
+/// so a post's ids can't clash with those of other posts shown on the same page
+pub fn prefix_ids(html: &str, prefix: &str) -> String {
+    let ids: HashSet<String> = tokenize(html)
+        .into_iter()
+        .filter_map(|t| match t {
+            Token::Tag(tag, _) if !tag.closing => tag.attr("id").map(String::from),
+            _ => None,
+        })
+        .collect();
+    rewrite_tags(html, |tag| {
+        let mut changed = false;
+        if let Some(id) = tag.attr("id").map(String::from) {
+            tag.set_attr("id", &(prefix.to_string() + &id));
+            changed = true;
+        }
+        if let Some(href) = tag.attr("href").map(String::from) {
+            if href.starts_with('#') && ids.contains(&href[1..]) {
+                tag.set_attr("href", &format!("#{}{}", prefix, &href[1..]));
+                changed = true;
+            }
+        }
+        changed
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{add_heading_ids, prefix_ids, Heading};
+
+    #[test]
+    fn ids() {
+        let html = "<h1>Title</h1>\n<h2>Intro</h2>\n<p>x</p>\n<h3>The <code>main</code> fn</h3>\n\
+                    <h2 id='custom'>Intro</h2>\n<h2>Intro</h2>\n<h4>日本語</h4>\n<h5>Deep</h5>";
+        let (out, headings) = add_heading_ids(html);
+        assert_eq!(
+            out,
+            "<h1>Title</h1>\n<h2 id=\"intro\">Intro</h2>\n<p>x</p>\n\
+             <h3 id=\"the-main-fn\">The <code>main</code> fn</h3>\n\
+             <h2 id='custom'>Intro</h2>\n<h2 id=\"intro-2\">Intro</h2>\n\
+             <h4 id=\"section\">日本語</h4>\n<h5>Deep</h5>"
+        );
+        assert_eq!(
+            headings[1],
+            Heading {
+                level: 3,
+                id: "the-main-fn".to_string(),
+                text: "The main fn".to_string(),
+            }
+        );
+        let ids: Vec<&str> = headings.iter().map(|h| h.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec!["intro", "the-main-fn", "custom", "intro-2", "section"]
+        );
+    }
+
+    #[test]
+    fn unbalanced() {
+        // A heading that's never closed gets no id, and neither does one with another inside it
+        let (out, headings) = add_heading_ids("<h2>Oops");
+        assert_eq!(out, "<h2>Oops");
+        assert!(headings.is_empty());
+        let (out, headings) = add_heading_ids("<h2>Outer <h3>Inner</h3></h2>\n<h2>Next</h2>");
+        assert_eq!(
+            out,
+            "<h2>Outer <h3 id=\"inner\">Inner</h3></h2>\n<h2 id=\"next\">Next</h2>"
+        );
+        let ids: Vec<&str> = headings.iter().map(|h| h.id.as_str()).collect();
+        assert_eq!(ids, vec!["inner", "next"]);
+    }
+
+    #[test]
+    fn prefixed() {
+        let (html, _) =
+            add_heading_ids("<h2>Intro</h2><a href='#intro'>up</a><a href='#site_nav'>nav</a>");
+        assert_eq!(
+            prefix_ids(&html, "abc-"),
+            "<h2 id=\"abc-intro\">Intro</h2><a href=\"#abc-intro\">up</a><a href='#site_nav'>nav</a>"
+        );
+    }
+}
@@ -31,6 +31,57 @@ pub fn recursive_find_files(d: &str) -> Vec<PathBuf> {
     v
 }
 
+/// The first file named *exe* in a directory in `$PATH`
+pub fn search_path(exe: &Path) -> Option<PathBuf> {
+    std::env::var_os("PATH").and_then(|paths| {
+        std::env::split_paths(&paths)
+            .filter_map(|dir| {
+                let p = dir.join(exe);
+                if p.is_file() {
+                    Some(p)
+                } else {
+                    None
+                }
+            })
+            .next()
+    })
+}
+
+/// Copy *src* to *dest*, creating directories as needed. Unless *force* is set, nothing is done if
+/// *dest* is the same size as *src* and was modified after it. Returns whether it copied.
+pub fn copy_if_changed(src: &Path, dest: &Path, force: bool) -> Result<bool, String> {
+    let src_meta = fs::metadata(src).map_err(|e| format!("{}: {}", src.display(), e))?;
+    if !force {
+        if let Ok(dest_meta) = fs::metadata(dest) {
+            let newer = match (src_meta.modified(), dest_meta.modified()) {
+                (Ok(s), Ok(d)) => d >= s,
+                _ => false,
+            };
+            if newer && src_meta.len() == dest_meta.len() {
+                return Ok(false);
+            }
+        }
+    }
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("{}: {}", parent.display(), e))?;
+    }
+    fs::copy(src, dest).map_err(|e| format!("{} -> {}: {}", src.display(), dest.display(), e))?;
+    Ok(true)
+}
+
+/// Write *bytes* to *dest*, creating directories as needed. Unless *force* is set, nothing is done
+/// if *dest* already holds *bytes*. Returns whether it wrote.
+pub fn write_if_changed(dest: &Path, bytes: &[u8], force: bool) -> Result<bool, String> {
+    if !force && fs::read(dest).map(|b| b == bytes).unwrap_or(false) {
+        return Ok(false);
+    }
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("{}: {}", parent.display(), e))?;
+    }
+    fs::write(dest, bytes).map_err(|e| format!("{}: {}", dest.display(), e))?;
+    Ok(true)
+}
+
 ///// Given a vec of files *paths*, return all the ones that have a filename matching *s*
 /////
 ///// If *s* is "foo", then "foo/bar" is not a match, but "foo" and "bar/foo" both are
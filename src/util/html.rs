@@ -0,0 +1,418 @@
+use std::fmt;
+
+/// Elements whose content is not HTML, so a `<` in them doesn't start a tag
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style"];
+
+/// Elements that can sit in the middle of a word, like `<em>un</em>likely`. Any other tag
+/// separates words.
+const INLINE_ELEMENTS: &[&str] = &[
+    "a", "abbr", "b", "code", "del", "em", "i", "ins", "kbd", "mark", "s", "small", "span",
+    "strong", "sub", "sup", "u",
+];
+
+/// A start or end tag
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tag {
+    /// Lowercased element name
+    pub name: String,
+    /// True for `</p>`
+    pub closing: bool,
+    /// True for `<br />`
+    pub self_closing: bool,
+    /// Lowercased attribute names with their unescaped values, in order. Attributes without a
+    /// value, like `<input disabled>`, have None.
+    pub attrs: Vec<(String, Option<String>)>,
+}
+impl Tag {
+    /// The value of the attribute *name*, if it's there and has one
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(n, _)| n == name)
+            .and_then(|(_, v)| v.as_deref())
+    }
+
+    pub fn has_attr(&self, name: &str) -> bool {
+        self.attrs.iter().any(|(n, _)| n == name)
+    }
+
+    /// Set the attribute *name* to *value*, adding it at the end if it isn't there
+    pub fn set_attr(&mut self, name: &str, value: &str) {
+        match self.attrs.iter_mut().find(|(n, _)| n == name) {
+            Some(attr) => attr.1 = Some(value.to_string()),
+            None => self.attrs.push((name.to_string(), Some(value.to_string()))),
+        }
+    }
+}
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<{}{}", if self.closing { "/" } else { "" }, self.name)?;
+        for (name, value) in &self.attrs {
+            match value {
+                Some(value) => write!(f, " {}=\"{}\"", name, escape(value))?,
+                None => write!(f, " {}", name)?,
+            }
+        }
+        write!(f, "{}>", if self.self_closing { " /" } else { "" })
+    }
+}
+
+/// A piece of an HTML document. Each holds the exact source text it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token<'a> {
+    /// Text between tags, still escaped
+    Text(&'a str),
+    Tag(Tag, &'a str),
+    /// Comments, doctypes and processing instructions
+    Other(&'a str),
+}
+impl<'a> Token<'a> {
+    pub fn source(&self) -> &'a str {
+        match self {
+            Token::Text(s) | Token::Other(s) | Token::Tag(_, s) => s,
+        }
+    }
+}
+
+/// Escape *s* for use in text or in a double-quoted attribute value
+pub fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out += "&amp;",
+            '<' => out += "&lt;",
+            '>' => out += "&gt;",
+            '"' => out += "&quot;",
+            '\'' => out += "&#39;",
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Undo `escape`, and also handle numeric character references and `&nbsp;`. Unknown entities
+/// are left alone.
+pub fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out += &rest[..amp];
+        rest = &rest[amp..];
+        let semi = match rest.find(';') {
+            Some(semi) if semi <= 10 => semi,
+            _ => {
+                out.push('&');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let name = &rest[1..semi];
+        let c = match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            _ if name.starts_with("#x") || name.starts_with("#X") => {
+                u32::from_str_radix(&name[2..], 16)
+                    .ok()
+                    .and_then(std::char::from_u32)
+            }
+            _ if name.starts_with('#') => name[1..].parse().ok().and_then(std::char::from_u32),
+            _ => None,
+        };
+        match c {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out + rest
+}
+
+/// Parse the tag starting at the `<` at the start of *s*, returning it and its length in bytes.
+/// Returns None if *s* doesn't start with something that looks like a tag.
+fn parse_tag(s: &str) -> Option<(Tag, usize)> {
+    let b = s.as_bytes();
+    let mut i = 1;
+    let closing = b.get(i) == Some(&b'/');
+    if closing {
+        i += 1;
+    }
+    if !b.get(i)?.is_ascii_alphabetic() {
+        return None;
+    }
+    let start = i;
+    while i < b.len() && (b[i].is_ascii_alphanumeric() || b[i] == b'-' || b[i] == b':') {
+        i += 1;
+    }
+    let mut tag = Tag {
+        name: s[start..i].to_lowercase(),
+        closing,
+        self_closing: false,
+        attrs: vec![],
+    };
+    loop {
+        while i < b.len() && b[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        match b.get(i)? {
+            b'>' => return Some((tag, i + 1)),
+            b'/' => {
+                i += 1;
+                if b.get(i) == Some(&b'>') {
+                    tag.self_closing = true;
+                    return Some((tag, i + 1));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        let start = i;
+        while i < b.len() && !b[i].is_ascii_whitespace() && !b"=>/".contains(&b[i]) {
+            i += 1;
+        }
+        let name = s[start..i].to_lowercase();
+        while i < b.len() && b[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if b.get(i) != Some(&b'=') {
+            tag.attrs.push((name, None));
+            continue;
+        }
+        i += 1;
+        while i < b.len() && b[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        let value = match b.get(i)? {
+            q @ b'"' | q @ b'\'' => {
+                let end = i + 1 + s[i + 1..].find(*q as char)?;
+                let v = &s[i + 1..end];
+                i = end + 1;
+                v
+            }
+            _ => {
+                let start = i;
+                while i < b.len() && !b[i].is_ascii_whitespace() && b[i] != b'>' {
+                    i += 1;
+                }
+                &s[start..i]
+            }
+        };
+        tag.attrs.push((name, Some(unescape(value))));
+    }
+}
+
+/// The unescaped text of *html*, with a space wherever a tag other than an inline one like `<em>`
+/// separates words. Everything inside *skip* elements is left out.
+pub fn text_content(html: &str, skip: &[&str]) -> String {
+    let mut text = String::new();
+    let mut skip_depth: usize = 0;
+    for token in tokenize(html) {
+        match token {
+            Token::Tag(tag, _) => {
+                if skip.contains(&tag.name.as_str()) && !tag.self_closing {
+                    if tag.closing {
+                        skip_depth = skip_depth.saturating_sub(1);
+                    } else {
+                        skip_depth += 1;
+                    }
+                }
+                if !INLINE_ELEMENTS.contains(&tag.name.as_str()) {
+                    text.push(' ');
+                }
+            }
+            Token::Text(s) if skip_depth == 0 => text += &unescape(s),
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Split *html* into text, tags and everything else. This is not a full HTML parser, but it
+/// handles what Markdown renderers and reb's templates produce.
+pub fn tokenize(html: &str) -> Vec<Token<'_>> {
+    let mut out = vec![];
+    let mut text_start = 0;
+    let mut i = 0;
+    while let Some(lt) = html[i..].find('<') {
+        let lt = i + lt;
+        let rest = &html[lt..];
+        let (token, len) = if rest.starts_with("<!--") {
+            let len = rest
+                .find("-->")
+                .map(|e| e + 3)
+                .unwrap_or_else(|| rest.len());
+            (Token::Other(&rest[..len]), len)
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            let len = rest.find('>').map(|e| e + 1).unwrap_or_else(|| rest.len());
+            (Token::Other(&rest[..len]), len)
+        } else if let Some((tag, len)) = parse_tag(rest) {
+            (Token::Tag(tag, &rest[..len]), len)
+        } else {
+            i = lt + 1;
+            continue;
+        };
+        if text_start < lt {
+            out.push(Token::Text(&html[text_start..lt]));
+        }
+        i = lt + len;
+        // Everything up to the matching end tag is text, whatever it looks like
+        let raw_end = match &token {
+            Token::Tag(tag, _) if !tag.closing && RAW_TEXT_ELEMENTS.contains(&&*tag.name) => {
+                let end = format!("</{}", tag.name);
+                Some(
+                    html[i..]
+                        .to_ascii_lowercase()
+                        .find(&end)
+                        .map(|e| i + e)
+                        .unwrap_or_else(|| html.len()),
+                )
+            }
+            _ => None,
+        };
+        out.push(token);
+        if let Some(end) = raw_end {
+            if i < end {
+                out.push(Token::Text(&html[i..end]));
+            }
+            i = end;
+        }
+        text_start = i;
+    }
+    if text_start < html.len() {
+        out.push(Token::Text(&html[text_start..]));
+    }
+    out
+}
+
+/// Call *f* with every tag in *html*, letting it change the tag. *f* returns whether it did, and
+/// tags it didn't change are left exactly as they were.
+pub fn rewrite_tags<F>(html: &str, mut f: F) -> String
+where
+    F: FnMut(&mut Tag) -> bool,
+{
+    let mut out = String::with_capacity(html.len());
+    for token in tokenize(html) {
+        match token {
+            Token::Tag(mut tag, src) => {
+                if f(&mut tag) {
+                    out += &tag.to_string();
+                } else {
+                    out += src;
+                }
+            }
+            token => out += token.source(),
+        }
+    }
+    out
+}
+
+/// Call *f* with the tag, attribute name and value of every attribute in *html*, replacing the
+/// value with whatever *f* returns if it returns something
+pub fn rewrite_attrs<F>(html: &str, mut f: F) -> String
+where
+    F: FnMut(&Tag, &str, &str) -> Option<String>,
+{
+    rewrite_tags(html, |tag| {
+        let mut changed = false;
+        for idx in 0..tag.attrs.len() {
+            let new = match &tag.attrs[idx] {
+                (name, Some(value)) => f(tag, name, value),
+                (_, None) => None,
+            };
+            if let Some(new) = new {
+                tag.attrs[idx].1 = Some(new);
+                changed = true;
+            }
+        }
+        changed
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape, rewrite_attrs, text_content, tokenize, unescape, Token};
+
+    #[test]
+    fn tokens() {
+        let html = "<!DOCTYPE html><p class=a id='b' hidden>1 < 2 &amp; <br/>x</p><!-- <i> -->";
+        let tokens = tokenize(html);
+        let sources: Vec<&str> = tokens.iter().map(|t| t.source()).collect();
+        assert_eq!(
+            sources,
+            vec![
+                "<!DOCTYPE html>",
+                "<p class=a id='b' hidden>",
+                "1 < 2 &amp; ",
+                "<br/>",
+                "x",
+                "</p>",
+                "<!-- <i> -->"
+            ]
+        );
+        assert_eq!(sources.concat(), html);
+        match &tokens[1] {
+            Token::Tag(tag, _) => {
+                assert_eq!(tag.name, "p");
+                assert_eq!(tag.attr("class"), Some("a"));
+                assert_eq!(tag.attr("id"), Some("b"));
+                assert_eq!(tag.attrs[2], ("hidden".to_string(), None));
+            }
+            _ => unreachable!(),
+        }
+        match &tokens[3] {
+            Token::Tag(tag, _) => assert!(tag.self_closing),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn raw_text() {
+        let tokens = tokenize("<script>if (a<b) {}</script><p>");
+        assert_eq!(tokens[1], Token::Text("if (a<b) {}"));
+        assert_eq!(tokens.len(), 4);
+    }
+
+    #[test]
+    fn entities() {
+        assert_eq!(
+            unescape("a &amp; b &lt;&#65;&#x42;&bogus; &"),
+            "a & b <AB&bogus; &"
+        );
+        assert_eq!(unescape(&escape("<\"'&>")), "<\"'&>");
+    }
+
+    #[test]
+    fn rewrite() {
+        let html = "<p><img src='a.png' alt=\"x\"> <a href=\"b.html?x=1&amp;y=2\">b</a></p>";
+        let out = rewrite_attrs(html, |tag, attr, value| {
+            if tag.name == "img" && attr == "src" {
+                Some(format!("/posts/{}", value))
+            } else {
+                None
+            }
+        });
+        assert_eq!(
+            out,
+            "<p><img src=\"/posts/a.png\" alt=\"x\"> <a href=\"b.html?x=1&amp;y=2\">b</a></p>"
+        );
+    }
+
+    #[test]
+    fn text() {
+        assert_eq!(
+            text_content(
+                "<p>un<em>like</em>ly &amp; <script>x</script></p><pre>code</pre><p>b</p>",
+                &["pre", "script"]
+            ),
+            " unlikely &       b "
+        );
+    }
+}
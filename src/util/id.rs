@@ -0,0 +1,151 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use chrono::{DateTime, TimeZone};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+/// How IDs for new posts are generated
+#[derive(Debug, PartialEq)]
+pub enum IdStrategy {
+    /// 8 random alphanumeric characters
+    Random,
+    /// One more than the largest numeric ID in use
+    Sequential,
+    /// The date followed by a counter, like 20261018-01
+    Date,
+    /// A ULID: 26 characters, sortable by creation time
+    Ulid,
+}
+impl FromStr for IdStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "random" => Ok(IdStrategy::Random),
+            "sequential" => Ok(IdStrategy::Sequential),
+            "date" => Ok(IdStrategy::Date),
+            "ulid" => Ok(IdStrategy::Ulid),
+            _ => Err(format!(
+                "Unknown ID strategy '{}', expected one of random, sequential, date, ulid",
+                s
+            )),
+        }
+    }
+}
+
+fn gen_random_id() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(8)
+        .collect::<String>()
+}
+
+const CROCKFORD: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+fn gen_ulid<Tz: TimeZone>(now: &DateTime<Tz>) -> String {
+    let ms = now.timestamp_millis() as u128 & ((1 << 48) - 1);
+    let rand_bits = rand::thread_rng().gen::<u128>() & ((1 << 80) - 1);
+    let mut n = (ms << 80) | rand_bits;
+    let mut out = [0u8; 26];
+    for c in out.iter_mut().rev() {
+        *c = CROCKFORD[(n & 0x1f) as usize];
+        n >>= 5;
+    }
+    String::from_utf8(out.to_vec()).unwrap()
+}
+
+/// Generate an ID for a new post with the given *strategy* that is not in *existing*
+pub fn gen_unique_id<Tz: TimeZone>(
+    strategy: &IdStrategy,
+    existing: &HashSet<String>,
+    now: &DateTime<Tz>,
+) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    match strategy {
+        IdStrategy::Random => loop {
+            let id = gen_random_id();
+            if !existing.contains(&id) {
+                return id;
+            }
+        },
+        IdStrategy::Ulid => loop {
+            let id = gen_ulid(now);
+            if !existing.contains(&id) {
+                return id;
+            }
+        },
+        IdStrategy::Sequential => {
+            let max = existing
+                .iter()
+                .filter_map(|id| id.parse::<u64>().ok())
+                .max()
+                .unwrap_or(0);
+            (max + 1).to_string()
+        }
+        IdStrategy::Date => {
+            let prefix = now.format("%Y%m%d").to_string();
+            (1..)
+                .map(|n| format!("{}-{:02}", prefix, n))
+                .find(|id| !existing.contains(id))
+                .unwrap()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{gen_unique_id, IdStrategy};
+    use chrono::{TimeZone, Utc};
+    use std::collections::HashSet;
+
+    fn set(ids: &[&str]) -> HashSet<String> {
+        ids.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_strategy() {
+        assert_eq!("ULID".parse::<IdStrategy>().unwrap(), IdStrategy::Ulid);
+        assert!("uuid".parse::<IdStrategy>().is_err());
+    }
+
+    #[test]
+    fn random() {
+        let now = Utc::now();
+        let id = gen_unique_id(&IdStrategy::Random, &set(&[]), &now);
+        assert_eq!(id.len(), 8);
+        assert!(id.chars().all(|c| c.is_ascii_alphanumeric()));
+    }
+
+    #[test]
+    fn sequential() {
+        let now = Utc::now();
+        let s = IdStrategy::Sequential;
+        assert_eq!(gen_unique_id(&s, &set(&[]), &now), "1");
+        assert_eq!(gen_unique_id(&s, &set(&["1", "7", "abc", "3"]), &now), "8");
+    }
+
+    #[test]
+    fn date() {
+        let now = Utc.ymd(2026, 10, 18).and_hms(12, 0, 0);
+        let s = IdStrategy::Date;
+        assert_eq!(gen_unique_id(&s, &set(&[]), &now), "20261018-01");
+        assert_eq!(
+            gen_unique_id(&s, &set(&["20261018-01", "20261018-02"]), &now),
+            "20261018-03"
+        );
+    }
+
+    #[test]
+    fn ulid() {
+        let now = Utc.ymd(2026, 10, 18).and_hms(12, 0, 0);
+        let a = gen_unique_id(&IdStrategy::Ulid, &set(&[]), &now);
+        let b = gen_unique_id(&IdStrategy::Ulid, &set(&[]), &now);
+        assert_eq!(a.len(), 26);
+        // Same timestamp, so same 10 character time prefix
+        assert_eq!(a[..10], b[..10]);
+        assert!(a.chars().all(|c| c.is_ascii_alphanumeric()));
+    }
+}
@@ -0,0 +1,48 @@
+/// Quote *s* as a JSON string
+pub fn quote(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            '\n' => out += "\\n",
+            '\t' => out += "\\t",
+            '\r' => out += "\\r",
+            c if (c as u32) < 0x20 => out += &format!("\\u{:04x}", c as u32),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Write the string pairs in *items* as a JSON object, one key per line
+pub fn object<'a, I>(items: I) -> String
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    let pairs: Vec<String> = items
+        .into_iter()
+        .map(|(k, v)| format!("  {}: {}", quote(k), quote(v)))
+        .collect();
+    if pairs.is_empty() {
+        "{}\n".to_string()
+    } else {
+        format!("{{\n{}\n}}\n", pairs.join(",\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{object, quote};
+
+    #[test]
+    fn quoting() {
+        assert_eq!(quote("a \"b\"\\\n\u{1}é"), "\"a \\\"b\\\"\\\\\\n\\u0001é\"");
+        assert_eq!(object(vec![]), "{}\n");
+        assert_eq!(
+            object(vec![("a", "b"), ("c", "d")]),
+            "{\n  \"a\": \"b\",\n  \"c\": \"d\"\n}\n"
+        );
+    }
+}
@@ -1,10 +1,6 @@
 pub mod fs;
-
-pub fn gen_id() -> String {
-    use rand::distributions::Alphanumeric;
-    use rand::Rng;
-    rand::thread_rng()
-        .sample_iter(&Alphanumeric)
-        .take(8)
-        .collect::<String>()
-}
+pub mod hash;
+pub mod html;
+pub mod id;
+pub mod json;
+pub mod slug;
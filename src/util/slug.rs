@@ -0,0 +1,193 @@
+/// Limits on how long generated slugs can get
+#[derive(Debug, Clone)]
+pub struct SlugOpts {
+    /// Maximum number of characters. Slugs are only ever cut between words.
+    pub max_len: usize,
+    /// Maximum number of words
+    pub max_words: usize,
+}
+
+/// ASCII spelling of common non-ASCII letters. Only lowercase letters are here, since everything
+/// is lowercased first.
+fn transliterate(c: char) -> Option<&'static str> {
+    Some(match c {
+        'à' | 'á' | 'â' | 'ã' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'ä' | 'æ' => "ae",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'ď' | 'đ' | 'ð' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+        'ĥ' | 'ħ' => "h",
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+        'ĵ' => "j",
+        'ķ' => "k",
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+        'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+        'ö' | 'œ' => "oe",
+        'ŕ' | 'ŗ' | 'ř' => "r",
+        'ś' | 'ŝ' | 'ş' | 'š' => "s",
+        'ß' => "ss",
+        'ţ' | 'ť' | 'ŧ' => "t",
+        'þ' => "th",
+        'ù' | 'ú' | 'û' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'ü' => "ue",
+        'ŵ' => "w",
+        'ý' | 'ÿ' | 'ŷ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        // Greek
+        'α' | 'ά' => "a",
+        'β' => "v",
+        'γ' => "g",
+        'δ' => "d",
+        'ε' | 'έ' => "e",
+        'ζ' => "z",
+        'η' | 'ή' => "i",
+        'θ' => "th",
+        'ι' | 'ί' | 'ϊ' | 'ΐ' => "i",
+        'κ' => "k",
+        'λ' => "l",
+        'μ' => "m",
+        'ν' => "n",
+        'ξ' => "x",
+        'ο' | 'ό' => "o",
+        'π' => "p",
+        'ρ' => "r",
+        'σ' | 'ς' => "s",
+        'τ' => "t",
+        'υ' | 'ύ' | 'ϋ' | 'ΰ' => "y",
+        'φ' => "f",
+        'χ' => "ch",
+        'ψ' => "ps",
+        'ω' | 'ώ' => "o",
+        // Cyrillic
+        'а' => "a",
+        'б' => "b",
+        'в' => "v",
+        'г' => "g",
+        'д' => "d",
+        'е' | 'э' => "e",
+        'ё' => "yo",
+        'ж' => "zh",
+        'з' => "z",
+        'и' | 'й' => "i",
+        'к' => "k",
+        'л' => "l",
+        'м' => "m",
+        'н' => "n",
+        'о' => "o",
+        'п' => "p",
+        'р' => "r",
+        'с' => "s",
+        'т' => "t",
+        'у' => "u",
+        'ф' => "f",
+        'х' => "kh",
+        'ц' => "ts",
+        'ч' => "ch",
+        'ш' => "sh",
+        'щ' => "shch",
+        'ы' => "y",
+        'ю' => "yu",
+        'я' => "ya",
+        'ъ' | 'ь' => "",
+        _ => return None,
+    })
+}
+
+/// Turn *s* into something safe to use in a filename or URL: lowercase ASCII letters and digits
+/// separated by single hyphens. Letters with a known ASCII spelling are transliterated,
+/// apostrophes are dropped so "don't" becomes "dont", and everything else separates words.
+pub fn slugify(s: &str) -> String {
+    let mut out = String::new();
+    let mut need_sep = false;
+    for c in s.chars().flat_map(|c| c.to_lowercase()) {
+        let piece = if c.is_ascii_alphanumeric() {
+            Some(c.to_string())
+        } else if c == '\'' || c == '\u{2019}' {
+            continue;
+        } else {
+            transliterate(c).map(|t| t.to_string())
+        };
+        match piece {
+            Some(p) => {
+                if need_sep && !out.is_empty() {
+                    out.push('-');
+                }
+                need_sep = false;
+                out += &p;
+            }
+            None => need_sep = true,
+        }
+    }
+    out
+}
+
+/// Like `slugify`, but also keep the slug within the limits in *opts*
+pub fn slugify_with_limits(s: &str, opts: &SlugOpts) -> String {
+    let mut out = String::new();
+    for word in slugify(s).split('-').take(opts.max_words) {
+        if word.is_empty() {
+            continue;
+        }
+        let len = if out.is_empty() {
+            word.len()
+        } else {
+            out.len() + 1 + word.len()
+        };
+        if len > opts.max_len {
+            // Always keep at least part of the first word
+            if out.is_empty() {
+                out += &word[..opts.max_len];
+            }
+            break;
+        }
+        if !out.is_empty() {
+            out.push('-');
+        }
+        out += word;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{slugify, slugify_with_limits, SlugOpts};
+
+    #[test]
+    fn basic() {
+        assert_eq!(slugify("Hello World"), "hello-world");
+        assert_eq!(slugify("  Hello,   World!! "), "hello-world");
+        assert_eq!(slugify("a/b\\c: d?e#f"), "a-b-c-d-e-f");
+        assert_eq!(slugify("Don't stop"), "dont-stop");
+        assert_eq!(slugify("Hi"), "hi");
+        assert_eq!(slugify("--__--"), "");
+    }
+
+    #[test]
+    fn transliteration() {
+        assert_eq!(slugify("Crème Brûlée"), "creme-brulee");
+        assert_eq!(slugify("Über Straße"), "ueber-strasse");
+        assert_eq!(slugify("Ελληνικά"), "ellinika");
+        assert_eq!(slugify("Привет мир"), "privet-mir");
+        // No known spelling, so it separates words
+        assert_eq!(slugify("rust 日本語 blog"), "rust-blog");
+    }
+
+    #[test]
+    fn limits() {
+        let opts = SlugOpts {
+            max_len: 15,
+            max_words: 3,
+        };
+        assert_eq!(
+            slugify_with_limits("one two three four", &opts),
+            "one-two-three"
+        );
+        assert_eq!(slugify_with_limits("alpha beta gamma", &opts), "alpha-beta");
+        assert_eq!(
+            slugify_with_limits("supercalifragilistic", &opts),
+            "supercalifragil"
+        );
+    }
+}
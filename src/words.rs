@@ -0,0 +1,120 @@
+use crate::util::html::text_content;
+
+/// Elements whose text isn't read like prose, so it isn't counted
+const SKIPPED_ELEMENTS: &[&str] = &["pre", "script", "style"];
+
+/// How many words a post has
+#[derive(Debug, Default, PartialEq)]
+pub struct WordCount {
+    /// Words separated by spaces
+    pub words: usize,
+    /// Chinese characters, kana and so on, which are read one at a time instead of as words
+    pub cjk_chars: usize,
+}
+impl WordCount {
+    pub fn total(&self) -> usize {
+        self.words + self.cjk_chars
+    }
+
+    /// Whole minutes it takes to read, rounded up, and at least 1
+    pub fn minutes(&self, words_per_minute: usize, cjk_chars_per_minute: usize) -> usize {
+        let minutes = self.words as f64 / words_per_minute.max(1) as f64
+            + self.cjk_chars as f64 / cjk_chars_per_minute.max(1) as f64;
+        (minutes.ceil() as usize).max(1)
+    }
+}
+
+/// Whether *c* is written without spaces between words
+fn is_cjk(c: char) -> bool {
+    match c as u32 {
+        0x3040..=0x30FF // hiragana and katakana
+        | 0x3400..=0x4DBF // CJK extension A
+        | 0x4E00..=0x9FFF // CJK unified ideographs
+        | 0xF900..=0xFAFF // CJK compatibility ideographs
+        | 0xFF66..=0xFF9F // halfwidth katakana
+        | 0x20000..=0x2FFFF => true, // CJK extensions B and on
+        _ => false,
+    }
+}
+
+/// Count the words in the text of *html*, leaving out markup and code blocks
+pub fn count_words(html: &str) -> WordCount {
+    let text = text_content(html, SKIPPED_ELEMENTS);
+    let mut count = WordCount::default();
+    for word in text.split_whitespace() {
+        let mut in_word = false;
+        for c in word.chars() {
+            if is_cjk(c) {
+                count.cjk_chars += 1;
+                in_word = false;
+            } else if c.is_alphanumeric() {
+                if !in_word {
+                    count.words += 1;
+                }
+                in_word = true;
+            }
+        }
+    }
+    count
+}
+
+/// The start of the text of *html*, about *len* characters of it
+pub fn excerpt(html: &str, len: usize) -> String {
+    snippet(&text_content(html, SKIPPED_ELEMENTS), len)
+}
+
+/// The first *len* characters or so of *text*, cut at a space
+pub fn snippet(text: &str, len: usize) -> String {
+    let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+    if text.chars().count() <= len {
+        return text;
+    }
+    let cut: String = text.chars().take(len).collect();
+    match cut.rfind(' ') {
+        Some(i) => cut[..i].to_string() + " …",
+        None => cut + "…",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{count_words, excerpt, snippet, WordCount};
+
+    #[test]
+    fn count() {
+        let html = "<h2>Hello, world</h2><p>It's <em>un</em>likely &amp; fine — 3.5</p>\
+                    <pre><code>let x = 1;</code></pre><p>See <code>main</code>.</p>";
+        assert_eq!(
+            count_words(html),
+            WordCount {
+                words: 8,
+                cjk_chars: 0
+            }
+        );
+        let c = count_words("<p>日本語のテキスト、Rustで書く</p>");
+        assert_eq!(
+            c,
+            WordCount {
+                words: 1,
+                cjk_chars: 11
+            }
+        );
+        assert_eq!(c.total(), 12);
+        let long = WordCount {
+            words: 401,
+            cjk_chars: 0,
+        };
+        assert_eq!(long.minutes(200, 500), 3);
+        assert_eq!(WordCount::default().minutes(200, 500), 1);
+    }
+
+    #[test]
+    fn snippets() {
+        assert_eq!(snippet("  a  b ", 10), "a b");
+        assert_eq!(snippet("one two three", 9), "one two …");
+        assert_eq!(
+            excerpt("<p>One <em>two</em></p><pre>three</pre><p>four</p>", 20),
+            "One two four"
+        );
+    }
+}